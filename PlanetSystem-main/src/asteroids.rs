@@ -0,0 +1,128 @@
+use nalgebra_glm::{Mat4, Vec3};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::f32::consts::PI;
+use crate::create_model_matrix;
+use crate::mesh::{icosphere, Mesh};
+
+pub struct AsteroidBelt {
+    asteroids: Vec<Asteroid>,
+    pub mesh: Mesh,
+}
+
+struct Asteroid {
+    orbit_radius: f32,
+    phase: f32,
+    height: f32,
+    orbital_speed: f32,
+    scale: Vec3,
+    rotation: Vec3,
+    spin: Vec3,
+}
+
+impl Asteroid {
+    /// roca con tamaño, forma y giro aleatorios
+    fn new(rng: &mut impl Rng, inner_radius: f32, outer_radius: f32) -> Self {
+        let size = rng.gen_range(0.08..0.35);
+
+        Asteroid {
+            orbit_radius: rng.gen_range(inner_radius..outer_radius),
+            phase: rng.gen::<f32>() * 2.0 * PI,
+            height: rng.gen_range(-0.6..0.6),
+            orbital_speed: rng.gen_range(0.10..0.13),
+            // escala distinta por eje para que no todas sean bolitas
+            scale: Vec3::new(
+                size * rng.gen_range(0.6..1.4),
+                size * rng.gen_range(0.6..1.4),
+                size * rng.gen_range(0.6..1.4),
+            ),
+            rotation: Vec3::new(
+                rng.gen::<f32>() * 2.0 * PI,
+                rng.gen::<f32>() * 2.0 * PI,
+                rng.gen::<f32>() * 2.0 * PI,
+            ),
+            spin: Vec3::new(
+                rng.gen_range(-0.05..0.05),
+                rng.gen_range(-0.05..0.05),
+                rng.gen_range(-0.05..0.05),
+            ),
+        }
+    }
+
+    fn model_matrix(&self, time: f32) -> Mat4 {
+        let angle = self.phase + time * self.orbital_speed;
+        let position = Vec3::new(
+            self.orbit_radius * angle.cos(),
+            self.height,
+            self.orbit_radius * angle.sin(),
+        );
+        let rotation = self.rotation + self.spin * time;
+
+        create_model_matrix(position, 1.0, rotation) * Mat4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+impl AsteroidBelt {
+    /// cinturón entre dos órbitas; la misma semilla da las mismas rocas
+    pub fn new(count: usize, inner_radius: f32, outer_radius: f32, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let asteroids = (0..count)
+            .map(|_| Asteroid::new(&mut rng, inner_radius, outer_radius))
+            .collect();
+
+        AsteroidBelt {
+            asteroids,
+            mesh: rock_mesh(&mut rng),
+        }
+    }
+
+    /// una matriz de modelo por roca para el render instanciado
+    pub fn instances(&self, time: f32) -> Vec<Mat4> {
+        self.asteroids
+            .iter()
+            .map(|asteroid| asteroid.model_matrix(time))
+            .collect()
+    }
+}
+
+// icosfera deformada con senos de fase aleatoria, se comparte entre todas las rocas
+fn rock_mesh(rng: &mut impl Rng) -> Mesh {
+    let phases = Vec3::new(
+        rng.gen::<f32>() * 2.0 * PI,
+        rng.gen::<f32>() * 2.0 * PI,
+        rng.gen::<f32>() * 2.0 * PI,
+    );
+    // depende solo de la dirección del vértice
+    let bump = |dir: Vec3| {
+        1.0 - 0.12 * ((dir.x * 3.0 + phases.x).sin() + (dir.y * 4.0 + phases.y).sin() + (dir.z * 5.0 + phases.z).sin()).abs()
+    };
+
+    let sphere = icosphere(1.0, 1);
+    let mut vertices = sphere.vertices;
+    for vertex in vertices.iter_mut() {
+        vertex.position *= bump(vertex.position);
+    }
+
+    // normal plana por cara para que se vea facetada
+    Mesh::new(vertices, sphere.indices).with_flat_normals()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rocks_stay_inside_the_belt() {
+        let belt = AsteroidBelt::new(500, 33.0, 37.0, 3);
+        for time in [0.0, 250.0, 10_000.0] {
+            let instances = belt.instances(time);
+            assert_eq!(instances.len(), 500);
+            for matrix in instances {
+                let position = matrix.column(3).xyz();
+                let radius = (position.x * position.x + position.z * position.z).sqrt();
+                assert!((33.0 - 1e-3..37.0 + 1e-3).contains(&radius), "roca en el radio {}", radius);
+                assert!(position.y.abs() <= 0.6);
+            }
+        }
+    }
+}
@@ -1,30 +1,65 @@
 use std::fmt;
 use std::ops::{Add, Mul};
+use crate::material::BlendMode;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    pub a: u8,
 }
 
 impl Color {
-    pub const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+    pub const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
 
     pub fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+        Color { r, g, b, a: 255 }
+    }
+
+    pub fn from_hex(hex: u32) -> Self {
+        let [_, r, g, b] = hex.to_be_bytes();
+        Color::new(r, g, b)
+    }
+
+    /// mismo color con otra opacidad (0 transparente, 1 opaco)
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Color { a: (alpha.clamp(0.0, 1.0) * 255.0).round() as u8, ..self }
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.a as f32 / 255.0
     }
 
     pub fn to_hex(self) -> u32 {
         u32::from_be_bytes([0, self.r, self.g, self.b])
     }
 
+    /// mezcla self (lo nuevo) encima de dst (lo que ya estaba en el buffer)
+    pub fn blend(self, dst: Color, mode: BlendMode) -> Color {
+        let alpha = self.alpha();
+        match mode {
+            BlendMode::Opaque => self,
+            BlendMode::Alpha => dst.lerp(&self, alpha),
+            BlendMode::Additive => dst + self * alpha,
+            BlendMode::Multiply => {
+                let product = Color::new(
+                    (self.r as u16 * dst.r as u16 / 255) as u8,
+                    (self.g as u16 * dst.g as u16 / 255) as u8,
+                    (self.b as u16 * dst.b as u16 / 255) as u8,
+                );
+                dst.lerp(&product, alpha)
+            }
+        }
+    }
+
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
           r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
           g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
           b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+          a: (self.a as f32 + (other.a as f32 - self.a as f32) * t).round() as u8,
         }
     }
 }
@@ -37,6 +72,7 @@ impl Add<Color> for Color {
             r: self.r.saturating_add(other.r),
             g: self.g.saturating_add(other.g),
             b: self.b.saturating_add(other.b),
+            a: self.a.max(other.a),
         }
     }
 }
@@ -49,12 +85,44 @@ impl Mul<f32> for Color {
             r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
             g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
             b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            a: self.a,
         }
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {}, g: {}, b: {}, a: {})", self.r, self.g, self.b, self.a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(color: Color) -> [u8; 3] {
+        [color.r, color.g, color.b]
+    }
+
+    #[test]
+    fn blend_modes_on_known_colors() {
+        let dst = Color::new(200, 100, 50);
+        let red = Color::new(255, 0, 0);
+
+        assert_eq!(rgb(red.blend(dst, BlendMode::Opaque)), [255, 0, 0]);
+
+        // alpha a la mitad queda entre los dos
+        assert_eq!(rgb(red.with_alpha(0.5).blend(dst, BlendMode::Alpha)), [228, 50, 25]);
+        assert_eq!(rgb(red.blend(dst, BlendMode::Alpha)), [255, 0, 0]);
+
+        // se suma lo nuevo por su alpha y se satura en 255
+        assert_eq!(rgb(Color::new(100, 50, 0).blend(dst, BlendMode::Additive)), [255, 150, 50]);
+        assert_eq!(rgb(Color::new(100, 50, 0).with_alpha(0.5).blend(dst, BlendMode::Additive)), [250, 125, 50]);
+
+        // el producto oscurece; con alpha 0 queda lo de atrás y a la mitad se mezcla
+        let gray = Color::new(128, 255, 0);
+        assert_eq!(rgb(gray.blend(dst, BlendMode::Multiply)), [100, 100, 0]);
+        assert_eq!(rgb(gray.with_alpha(0.0).blend(dst, BlendMode::Multiply)), [200, 100, 50]);
+        assert_eq!(rgb(Color::new(0, 0, 0).with_alpha(0.5).blend(dst, BlendMode::Multiply)), [100, 50, 25]);
     }
 }
@@ -0,0 +1,171 @@
+use std::fmt;
+use crate::triangle::Rasterizer;
+
+/// suavizado de bordes que se elige por línea de comandos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Antialiasing {
+    None,
+    /// se renderiza a N× en cada eje y se promedia
+    Ssaa(usize),
+    /// N muestras de cobertura/profundidad por pixel, un solo shading
+    Msaa(usize),
+    /// post-proceso sobre la imagen final
+    Fxaa,
+}
+
+impl Antialiasing {
+    pub fn ssaa_factor(self) -> usize {
+        match self {
+            Antialiasing::Ssaa(factor) => factor,
+            _ => 1,
+        }
+    }
+
+    pub fn msaa_samples(self) -> usize {
+        match self {
+            Antialiasing::Msaa(samples) => samples,
+            _ => 1,
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(Antialiasing::None),
+            "fxaa" => Ok(Antialiasing::Fxaa),
+            "ssaa2" => Ok(Antialiasing::Ssaa(2)),
+            "ssaa3" => Ok(Antialiasing::Ssaa(3)),
+            "ssaa4" => Ok(Antialiasing::Ssaa(4)),
+            "msaa2" => Ok(Antialiasing::Msaa(2)),
+            "msaa4" => Ok(Antialiasing::Msaa(4)),
+            "msaa8" => Ok(Antialiasing::Msaa(8)),
+            other => Err(format!("modo de antialiasing desconocido: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Antialiasing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Antialiasing::None => write!(f, "NONE"),
+            Antialiasing::Ssaa(factor) => write!(f, "SSAA {}X", factor),
+            Antialiasing::Msaa(samples) => write!(f, "MSAA {}X", samples),
+            Antialiasing::Fxaa => write!(f, "FXAA"),
+        }
+    }
+}
+
+fn parse_rasterizer(value: &str) -> Result<Rasterizer, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "scalar" => Ok(Rasterizer::Scalar),
+        "simd" => Ok(Rasterizer::Simd),
+        other => Err(format!("rasterizador desconocido: {}", other)),
+    }
+}
+
+/// opciones de arranque
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub antialiasing: Antialiasing,
+    pub rasterizer: Rasterizer,
+    /// dibujar primero solo la profundidad de lo opaco
+    pub depth_prepass: bool,
+    /// Some(n): sin ventana, se renderizan n frames con cada rasterizador y se comparan
+    pub bench_frames: Option<u32>,
+    /// de aquí sale todo lo que se genera al azar, misma semilla = misma imagen
+    pub seed: u64,
+}
+
+pub const USAGE: &str = "uso: lab5-shaders [--aa none|fxaa|ssaa2|ssaa3|ssaa4|msaa2|msaa4|msaa8] [--raster scalar|simd] [--depth-prepass] [--bench FRAMES] [--seed N]";
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            antialiasing: Antialiasing::None,
+            rasterizer: Rasterizer::Simd,
+            depth_prepass: false,
+            bench_frames: None,
+            seed: 1,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args() -> Result<Self, String> {
+        Config::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--aa" => {
+                    let value = args.next().ok_or("--aa necesita un valor")?;
+                    config.antialiasing = Antialiasing::parse(&value)?;
+                }
+                "--raster" => {
+                    let value = args.next().ok_or("--raster necesita un valor")?;
+                    config.rasterizer = parse_rasterizer(&value)?;
+                }
+                "--depth-prepass" => config.depth_prepass = true,
+                "--bench" => {
+                    let value = args.next().ok_or("--bench necesita la cantidad de frames")?;
+                    let frames = value.parse().map_err(|_| format!("cantidad de frames inválida: {}", value))?;
+                    config.bench_frames = Some(frames);
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed necesita un número")?;
+                    config.seed = value.parse().map_err(|_| format!("semilla inválida: {}", value))?;
+                }
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                other => return Err(format!("argumento desconocido: {}", other)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, String> {
+        Config::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_every_flag() {
+        let config = parse(&["--aa", "MSAA4", "--raster", "scalar", "--depth-prepass", "--bench", "30", "--seed", "42"]).unwrap();
+        assert_eq!(config.antialiasing, Antialiasing::Msaa(4));
+        assert_eq!(config.rasterizer, Rasterizer::Scalar);
+        assert!(config.depth_prepass);
+        assert_eq!(config.bench_frames, Some(30));
+        assert_eq!(config.seed, 42);
+
+        // sin nada quedan los de por defecto
+        let config = parse(&[]).unwrap();
+        assert_eq!(config.antialiasing, Antialiasing::None);
+        assert_eq!(config.rasterizer, Rasterizer::Simd);
+        assert!(!config.depth_prepass);
+        assert_eq!(config.bench_frames, None);
+        assert_eq!(parse(&["--aa", "ssaa2"]).unwrap().antialiasing.ssaa_factor(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        for args in [
+            &["--aa", "msaa3"][..],
+            &["--aa"],
+            &["--raster", "gpu"],
+            &["--bench", "muchos"],
+            &["--bench", "-1"],
+            &["--seed", "semilla"],
+            &["--seed"],
+            &["--fullscreen"],
+        ] {
+            assert!(parse(args).is_err(), "{:?} debería fallar", args);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use nalgebra_glm::{Vec2, Vec3};
+use crate::texture::Texture;
+
+/// seis caras en el orden +X, -X, +Y, -Y, +Z, -Z, con la orientación de OpenGL
+pub struct Cubemap {
+    faces: [Texture; 6],
+}
+
+impl Cubemap {
+    pub fn new(faces: [Texture; 6]) -> Self {
+        Cubemap { faces }
+    }
+
+    pub fn load(paths: [&str; 6]) -> image::ImageResult<Self> {
+        let [px, nx, py, ny, pz, nz] = paths;
+        Ok(Cubemap::new([
+            Texture::load(px)?,
+            Texture::load(nx)?,
+            Texture::load(py)?,
+            Texture::load(ny)?,
+            Texture::load(pz)?,
+            Texture::load(nz)?,
+        ]))
+    }
+
+    /// cada texel con el color de su dirección, para hornear cielos procedurales
+    pub fn from_fn(size: usize, color: impl Fn(Vec3) -> Vec3) -> Self {
+        let face = |face: usize| {
+            let pixels = (0..size * size)
+                .map(|i| {
+                    let uv = Vec2::new(((i % size) as f32 + 0.5) / size as f32, ((i / size) as f32 + 0.5) / size as f32);
+                    color(face_direction(face, uv).normalize())
+                })
+                .collect();
+            Texture::new(size, size, pixels)
+        };
+        Cubemap::new([face(0), face(1), face(2), face(3), face(4), face(5)])
+    }
+
+    /// color en una dirección, no hace falta que venga normalizada
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let (face, uv) = face_uv(direction);
+        self.faces[face].sample_clamped(uv)
+    }
+}
+
+/// la cara por el eje más largo y la uv dentro de ella (v hacia abajo como en la imagen)
+pub fn face_uv(d: Vec3) -> (usize, Vec2) {
+    let (x, y, z) = (d.x.abs(), d.y.abs(), d.z.abs());
+    let (face, s, t, major) = if x >= y && x >= z {
+        if d.x > 0.0 { (0, -d.z, -d.y, x) } else { (1, d.z, -d.y, x) }
+    } else if y >= z {
+        if d.y > 0.0 { (2, d.x, d.z, y) } else { (3, d.x, -d.z, y) }
+    } else if d.z > 0.0 {
+        (4, d.x, -d.y, z)
+    } else {
+        (5, -d.x, -d.y, z)
+    };
+    (face, Vec2::new((s / major + 1.0) * 0.5, (t / major + 1.0) * 0.5))
+}
+
+/// lo contrario de face_uv: la dirección (sin normalizar) de un punto de la cara
+pub fn face_direction(face: usize, uv: Vec2) -> Vec3 {
+    let (s, t) = (uv.x * 2.0 - 1.0, uv.y * 2.0 - 1.0);
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axes_hit_face_centers() {
+        let axes = [Vec3::x(), -Vec3::x(), Vec3::y(), -Vec3::y(), Vec3::z(), -Vec3::z()];
+        for (face, axis) in axes.iter().enumerate() {
+            assert_eq!(face_uv(*axis), (face, Vec2::new(0.5, 0.5)));
+        }
+    }
+
+    #[test]
+    fn baked_directions_come_back() {
+        let cubemap = Cubemap::from_fn(16, |d| d * 0.5 + Vec3::repeat(0.5));
+        for d in [Vec3::new(0.3, -0.8, 0.5), Vec3::new(-0.9, 0.1, 0.2), Vec3::new(0.1, 0.2, -0.97)] {
+            let d = d.normalize();
+            assert!((cubemap.sample(d) - (d * 0.5 + Vec3::repeat(0.5))).magnitude() < 0.05);
+        }
+    }
+
+    #[test]
+    fn neighbouring_faces_meet_at_their_edges() {
+        // el borde izquierdo de +X toca el derecho de +Z
+        let (face, uv) = face_uv(Vec3::new(1.0, 0.0, 0.999));
+        assert_eq!(face, 0);
+        assert!(uv.x < 0.001);
+        let (face, uv) = face_uv(Vec3::new(0.999, 0.0, 1.0));
+        assert_eq!(face, 4);
+        assert!(uv.x > 0.999);
+
+        // arriba de +Z (v = 0) sigue la parte de abajo de +Y (v = 1)
+        let (face, uv) = face_uv(Vec3::new(0.0, 0.999, 1.0));
+        assert_eq!(face, 4);
+        assert!(uv.y < 0.001);
+        let (face, uv) = face_uv(Vec3::new(0.0, 1.0, 0.999));
+        assert_eq!(face, 2);
+        assert!(uv.y > 0.999);
+    }
+}
@@ -0,0 +1,139 @@
+use nalgebra_glm::{Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::noise;
+use crate::texture::{equirectangular_uv, Texture};
+use crate::vertex::Vertex;
+
+/// de dónde sale la altura del terreno, de -1 a 1 sobre la posición en el modelo
+pub enum HeightSource {
+    // una función sobre la posición, como los cráteres de la luna
+    Procedural(fn(Vec3) -> f32),
+    // montañas de terrain_noise con esta semilla
+    Noise(u32),
+    // gris de una imagen equirectangular, como las uv de la esfera
+    Heightmap(Texture),
+}
+
+/// empuja los vértices por su normal, así el relieve cambia la silueta. el terreno fijo se hornea
+/// una vez al cargar (displace_mesh); con una altura animada o que llega por partes se cuelga del
+/// material y se aplica en el vertex stage (ver VertexCache::fetch)
+pub struct Displacement {
+    pub source: HeightSource,
+    // cuánto se mueve el vértice con altura 1, en unidades del modelo
+    pub amplitude: f32,
+}
+
+impl Displacement {
+    pub fn procedural(height: fn(Vec3) -> f32, amplitude: f32) -> Self {
+        Displacement { source: HeightSource::Procedural(height), amplitude }
+    }
+
+    /// la imagen si se puede leer, si no montañas con la semilla
+    pub fn from_image_or_noise(path: &str, seed: u32, amplitude: f32) -> Self {
+        let source = match Texture::load(path) {
+            Ok(texture) => HeightSource::Heightmap(texture),
+            Err(_) => HeightSource::Noise(seed),
+        };
+        Displacement { source, amplitude }
+    }
+
+    pub fn height(&self, p: Vec3) -> f32 {
+        let height = match &self.source {
+            HeightSource::Procedural(height) => height(p),
+            HeightSource::Noise(seed) => terrain_noise(p, *seed),
+            // misma vuelta que las uv de uv_sphere
+            HeightSource::Heightmap(texture) => texture.sample(equirectangular_uv(p)).mean() * 2.0 - 1.0,
+        };
+        height.clamp(-1.0, 1.0)
+    }
+
+    /// lo más que se puede salir un vértice, para agrandar la esfera del culling
+    pub fn max_offset(&self) -> f32 {
+        self.amplitude.abs()
+    }
+
+    /// copia del mesh con todos los vértices movidos; el radio para el culling sale ya con el relieve
+    pub fn displace_mesh(&self, mesh: &Mesh) -> Mesh {
+        Mesh::new(mesh.vertices.iter().map(|vertex| self.displace(vertex)).collect(), mesh.indices.clone())
+    }
+
+    /// vértice movido por su normal, con la normal y la tangente de la superficie ya movida
+    pub fn displace(&self, vertex: &Vertex) -> Vertex {
+        const STEP: f32 = 1e-3;
+        let p = vertex.position;
+        let normal = vertex.normal;
+
+        // dos direcciones sobre el plano tangente; sin tangente cualquier perpendicular sirve
+        let mut tangent = vertex.tangent.xyz() - normal * normal.dot(&vertex.tangent.xyz());
+        if tangent.magnitude_squared() < 1e-12 {
+            let axis = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+            tangent = axis - normal * normal.dot(&axis);
+        }
+        let tangent = tangent.normalize();
+        let bitangent = normal.cross(&tangent);
+
+        // pendiente de la altura sobre la superficie; la curvatura ya la trae la normal original
+        let slope = |direction: Vec3| {
+            (self.height(p + direction * STEP) - self.height(p - direction * STEP)) / (2.0 * STEP) * self.amplitude
+        };
+        let new_normal = (normal - tangent * slope(tangent) - bitangent * slope(bitangent)).normalize();
+        let new_tangent = (tangent - new_normal * new_normal.dot(&tangent)).normalize();
+
+        Vertex {
+            position: p + normal * self.height(p) * self.amplitude,
+            normal: new_normal,
+            tangent: Vec4::new(new_tangent.x, new_tangent.y, new_tangent.z, if vertex.tangent.w < 0.0 { -1.0 } else { 1.0 }),
+            ..vertex.clone()
+        }
+    }
+}
+
+/// montañas: ruido de valor con varias octavas, más o menos de -1 a 1
+pub fn terrain_noise(p: Vec3, seed: u32) -> f32 {
+    let sum = noise::fbm(p * 4.0, 5, seed);
+    // los valles se aplanan y las crestas quedan más marcadas
+    let ridged = 1.0 - (sum * 2.0).abs();
+    ridged * ridged * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    fn heightmap(width: usize, height: usize, pixels: Vec<Vec3>, amplitude: f32) -> Displacement {
+        Displacement { source: HeightSource::Heightmap(Texture::new(width, height, pixels)), amplitude }
+    }
+
+    // en el ecuador mirando a +X, donde la imagen tiene u = 0 y v = 0.5
+    fn equator_vertex() -> Vertex {
+        Vertex::new(Vec3::new(0.5, 0.0, 0.0), Vec3::x(), Vec2::zeros())
+    }
+
+    #[test]
+    fn flat_height_only_moves_along_normal() {
+        // gris 0.75 es altura 0.5
+        let displaced = heightmap(1, 1, vec![Vec3::repeat(0.75)], 0.1).displace(&equator_vertex());
+        assert!((displaced.position - Vec3::new(0.55, 0.0, 0.0)).magnitude() < 1e-6);
+        assert!((displaced.normal - Vec3::x()).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn normal_leans_away_from_slope() {
+        // blanco arriba (norte) y negro abajo: sube hacia +y, la normal tiene que irse hacia -y
+        let displacement = heightmap(1, 2, vec![Vec3::repeat(1.0), Vec3::zeros()], 0.5);
+        let displaced = displacement.displace(&equator_vertex());
+        assert!(displacement.height(Vec3::new(0.5, 0.0, 0.0)).abs() < 1e-5);
+        assert!(displaced.normal.y < -0.1);
+        assert!((displaced.normal.magnitude() - 1.0).abs() < 1e-5);
+        assert!(displaced.tangent.xyz().dot(&displaced.normal).abs() < 1e-5);
+    }
+
+    #[test]
+    fn displaced_mesh_grows_its_culling_radius() {
+        let sphere = crate::mesh::uv_sphere(0.5, 8, 4);
+        let displaced = heightmap(1, 1, vec![Vec3::repeat(1.0)], 0.1).displace_mesh(&sphere);
+        assert_eq!(displaced.indices, sphere.indices);
+        assert!((displaced.radius - 0.6).abs() < 1e-5);
+    }
+}
@@ -1,28 +1,21 @@
-use nalgebra_glm::{Vec3, Vec2};
+use nalgebra_glm::{Vec3, Vec2, Vec4};
+
+// luz fija de los shaders que usan intensity, en mundo
+pub const LIGHT_DIR: Vec3 = Vec3::new(0.0, 0.0, 1.0);
 
 #[derive(Debug, Clone, Copy)]
 pub struct Fragments {
     pub position: Vec2,
     pub depth: f32,
     pub normal: Vec3,
+    // en mundo como la normal, w es el signo de la bitangente; cero si el mesh no trae
+    pub tangent: Vec4,
     pub intensity: f32,
     pub vertex_pos: Vec3,
+    // uv interpolado de los vértices, para las texturas del .mtl
+    pub tex_coords: Vec2,
+    // bit i prendido = la muestra i del pixel está dentro del triángulo
+    pub coverage: u8,
+    // cuánto cambia la profundidad por pixel en x y en y
+    pub depth_slope: Vec2,
 }
-
-impl Fragments {
-    pub fn new(
-        position: Vec2,
-        depth: f32,
-        normal: Vec3,
-        intensity: f32,
-        vertex_pos: Vec3,
-    ) -> Self {
-        Fragments {
-            position,
-            depth,
-            normal,
-            intensity,
-            vertex_pos,
-        }
-    }
-}
\ No newline at end of file
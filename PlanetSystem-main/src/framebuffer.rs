@@ -1,24 +1,76 @@
+use nalgebra_glm::Vec2;
+use crate::color::Color;
+use crate::fragments::Fragments;
+use crate::material::BlendMode;
+use crate::triangle::Rasterizer;
+
+// posiciones de las muestras dentro del pixel (patrones estándar de D3D)
+const SAMPLES_1: [(f32, f32); 1] = [(0.5, 0.5)];
+const SAMPLES_2: [(f32, f32); 2] = [(0.75, 0.75), (0.25, 0.25)];
+const SAMPLES_4: [(f32, f32); 4] = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+const SAMPLES_8: [(f32, f32); 8] = [
+    (0.5625, 0.3125), (0.4375, 0.6875), (0.8125, 0.5625), (0.3125, 0.1875),
+    (0.1875, 0.8125), (0.0625, 0.4375), (0.6875, 0.9375), (0.9375, 0.0625),
+];
+
+/// qué pasada se está dibujando, cambia cómo se prueba y escribe la profundidad
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthPass {
+    /// color y profundidad a la vez
+    Single,
+    /// solo la profundidad de lo opaco, sin sombrear nada
+    PrePass,
+    /// el color después del pre-pass: cada pixel se sombrea una sola vez
+    AfterPrePass,
+}
+
+/// más muestras por pixel que esto no hay (la cobertura va en un u8)
+pub const MAX_SAMPLES: usize = SAMPLES_8.len();
+
+/// buffer y zbuffer guardan `samples` valores seguidos por pixel
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    pub samples: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    sample_offsets: Vec<Vec2>,
+    // con qué se rasterizan los triángulos que van a este framebuffer
+    pub rasterizer: Rasterizer,
+    pub depth_pass: DepthPass,
     background_color: u32,
     current_color: u32,
 }
 
 impl Framebuffer {
-    pub fn new(width: usize, height: usize) -> Self {
+    /// samples es 1 sin MSAA, o 2, 4 u 8
+    pub fn with_samples(width: usize, height: usize, samples: usize) -> Self {
+        let offsets: &[(f32, f32)] = match samples {
+            2 => &SAMPLES_2,
+            4 => &SAMPLES_4,
+            8 => &SAMPLES_8,
+            _ => &SAMPLES_1,
+        };
+        let samples = offsets.len();
+
         Framebuffer {
             width,
             height,
-            buffer: vec![0; width * height],
-            zbuffer: vec![f32::INFINITY; width * height],
+            samples,
+            buffer: vec![0; width * height * samples],
+            zbuffer: vec![f32::INFINITY; width * height * samples],
+            sample_offsets: offsets.iter().map(|&(x, y)| Vec2::new(x, y)).collect(),
+            rasterizer: Rasterizer::Simd,
+            depth_pass: DepthPass::Single,
             background_color: 0x000000,
             current_color: 0xFFFFFF,
         }
     }
 
+    pub fn sample_offsets(&self) -> &[Vec2] {
+        &self.sample_offsets
+    }
+
     pub fn clear(&mut self) {
         for pixel in self.buffer.iter_mut() {
             *pixel = self.background_color;
@@ -28,16 +80,119 @@ impl Framebuffer {
         }
     }
 
+    /// pinta todas las muestras del pixel (nada en el pre-pass, los puntos no tapan)
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+        if x < self.width && y < self.height && self.depth_pass != DepthPass::PrePass {
+            let first = (y * self.width + x) * self.samples;
+            for index in first..first + self.samples {
+                if self.zbuffer[index] > depth {
+                    self.buffer[index] = self.current_color;
+                    self.zbuffer[index] = depth;
+                }
+            }
+        }
+    }
+
+    /// como point pero suma el color a lo que ya había (estrellas sobre el fondo del cielo)
+    pub fn point_additive(&mut self, x: usize, y: usize, depth: f32) {
+        if x < self.width && y < self.height && self.depth_pass != DepthPass::PrePass {
+            let first = (y * self.width + x) * self.samples;
+            let color = Color::from_hex(self.current_color);
+            for index in first..first + self.samples {
+                if self.zbuffer[index] > depth {
+                    self.buffer[index] = (Color::from_hex(self.buffer[index]) + color).to_hex();
+                    self.zbuffer[index] = depth;
+                }
             }
         }
     }
 
+    /// pinta solo las muestras donde todavía no se dibujó nada y no escribe profundidad,
+    /// así todo lo demás queda adelante; el color se calcula solo si hay dónde ponerlo
+    pub fn paint_behind(&mut self, x: usize, y: usize, color: impl FnOnce() -> u32) {
+        if x >= self.width || y >= self.height || self.depth_pass == DepthPass::PrePass {
+            return;
+        }
+        let first = (y * self.width + x) * self.samples;
+        let samples = first..first + self.samples;
+        if self.zbuffer[samples.clone()].iter().all(|&depth| depth != f32::INFINITY) {
+            return;
+        }
+        let color = color();
+        for index in samples {
+            if self.zbuffer[index] == f32::INFINITY {
+                self.buffer[index] = color;
+            }
+        }
+    }
+
+    /// early-z: prueba la profundidad de las muestras que cubre el fragmento (y la escribe si
+    /// depth_write, antes de sombrear); devuelve las muestras que pasaron
+    pub fn depth_test(&mut self, fragment: &Fragments, depth_write: bool) -> u8 {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+
+        let first = (y * self.width + x) * self.samples;
+        let mut passed = 0;
+        for (sample, offset) in self.sample_offsets.iter().enumerate() {
+            if fragment.coverage & (1 << sample) == 0 {
+                continue;
+            }
+            let index = first + sample;
+            let depth = fragment.depth
+                + fragment.depth_slope.x * (offset.x - 0.5)
+                + fragment.depth_slope.y * (offset.y - 0.5);
+            let visible = match self.depth_pass {
+                DepthPass::Single | DepthPass::PrePass => depth < self.zbuffer[index],
+                // la profundidad ya es la del pre-pass, pasa justo lo que quedó adelante
+                DepthPass::AfterPrePass => depth <= self.zbuffer[index],
+            };
+            if visible {
+                passed |= 1 << sample;
+                if depth_write && self.depth_pass != DepthPass::AfterPrePass {
+                    self.zbuffer[index] = depth;
+                }
+            }
+        }
+        passed
+    }
+
+    /// mezcla el color en las muestras de coverage, la profundidad ya se probó con depth_test
+    pub fn blend_samples(&mut self, fragment: &Fragments, coverage: u8, color: Color, blend_mode: BlendMode) {
+        let first = (fragment.position.y as usize * self.width + fragment.position.x as usize) * self.samples;
+        for sample in 0..self.samples {
+            if coverage & (1 << sample) != 0 {
+                let index = first + sample;
+                let dst = Color::from_hex(self.buffer[index]);
+                self.buffer[index] = color.blend(dst, blend_mode).to_hex();
+            }
+        }
+    }
+
+    /// promedio de las muestras de cada pixel, lista para mostrar
+    pub fn resolve(&self) -> Vec<u32> {
+        if self.samples == 1 {
+            return self.buffer.clone();
+        }
+
+        let count = self.samples as u32;
+        self.buffer
+            .chunks_exact(self.samples)
+            .map(|pixel| {
+                let (mut r, mut g, mut b) = (0, 0, 0);
+                for &sample in pixel {
+                    r += (sample >> 16) & 0xFF;
+                    g += (sample >> 8) & 0xFF;
+                    b += sample & 0xFF;
+                }
+                (r / count) << 16 | (g / count) << 8 | (b / count)
+            })
+            .collect()
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
@@ -46,3 +201,51 @@ impl Framebuffer {
         self.current_color = color;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3, Vec4};
+
+    fn fragment(depth: f32) -> Fragments {
+        Fragments {
+            position: Vec2::new(1.0, 1.0),
+            depth,
+            normal: Vec3::z(),
+            tangent: Vec4::zeros(),
+            intensity: 1.0,
+            vertex_pos: Vec3::zeros(),
+            tex_coords: Vec2::zeros(),
+            coverage: 1,
+            depth_slope: Vec2::zeros(),
+        }
+    }
+
+    #[test]
+    fn depth_test_keeps_the_nearest() {
+        let mut framebuffer = Framebuffer::with_samples(4, 4, 1);
+        assert_eq!(framebuffer.depth_test(&fragment(0.5), true), 1);
+        // más lejos no pasa, más cerca sí
+        assert_eq!(framebuffer.depth_test(&fragment(0.7), true), 0);
+        assert_eq!(framebuffer.depth_test(&fragment(0.3), true), 1);
+        assert_eq!(framebuffer.zbuffer[4 + 1], 0.3);
+    }
+
+    #[test]
+    fn depth_test_without_write_leaves_the_zbuffer() {
+        let mut framebuffer = Framebuffer::with_samples(4, 4, 1);
+        assert_eq!(framebuffer.depth_test(&fragment(0.5), false), 1);
+        assert_eq!(framebuffer.zbuffer[4 + 1], f32::INFINITY);
+    }
+
+    #[test]
+    fn after_the_pre_pass_only_the_stored_depth_passes() {
+        let mut framebuffer = Framebuffer::with_samples(4, 4, 1);
+        framebuffer.depth_pass = DepthPass::PrePass;
+        framebuffer.depth_test(&fragment(0.4), true);
+
+        framebuffer.depth_pass = DepthPass::AfterPrePass;
+        assert_eq!(framebuffer.depth_test(&fragment(0.4), true), 1);
+        assert_eq!(framebuffer.depth_test(&fragment(0.6), true), 0);
+    }
+}
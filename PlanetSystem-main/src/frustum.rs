@@ -0,0 +1,65 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+/// los 6 planos de la cámara, con la normal apuntando hacia adentro
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// saca los planos de projection * view (método de Gribb/Hartmann)
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| {
+            let length = plane.xyz().magnitude();
+            plane / length
+        });
+
+        Frustum { planes }
+    }
+
+    /// false si la esfera queda toda afuera de algún plano
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(&center) + plane.w >= -radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm as glm;
+
+    #[test]
+    fn planes_of_an_orthographic_box() {
+        // caja de -1 a 1 en x e y, mirando hacia -z de 1 a 10
+        let frustum = Frustum::from_matrix(&glm::ortho(-1.0, 1.0, -1.0, 1.0, 1.0, 10.0));
+        let expected = [
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+            Vec4::new(-1.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.0, 1.0, 0.0, 1.0),
+            Vec4::new(0.0, -1.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, -1.0, -1.0),
+            Vec4::new(0.0, 0.0, 1.0, 10.0),
+        ];
+        for (plane, expected) in frustum.planes.iter().zip(expected) {
+            assert!((plane - expected).magnitude() < 1e-5, "{:?} != {:?}", plane, expected);
+        }
+    }
+
+    #[test]
+    fn spheres_inside_outside_and_across_a_plane() {
+        let view = glm::look_at(&Vec3::new(0.0, 0.0, 10.0), &Vec3::zeros(), &Vec3::y());
+        let projection = glm::perspective(1.0, 45f32.to_radians(), 0.1, 100.0);
+        let frustum = Frustum::from_matrix(&(projection * view));
+
+        assert!(frustum.intersects_sphere(Vec3::zeros(), 1.0));
+        // detrás de la cámara
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 20.0), 1.0));
+        // muy a la derecha: afuera, pero con el radio suficiente cruza el plano y cuenta
+        assert!(!frustum.intersects_sphere(Vec3::new(20.0, 0.0, 0.0), 1.0));
+        assert!(frustum.intersects_sphere(Vec3::new(20.0, 0.0, 0.0), 20.0));
+    }
+}
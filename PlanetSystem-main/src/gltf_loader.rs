@@ -0,0 +1,478 @@
+use nalgebra_glm::{self as glm, Mat4, Quat, Vec2, Vec3, Vec4};
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::Interpolation;
+use gltf::mesh::Mode;
+use std::rc::Rc;
+use crate::load_error::LoadError;
+use crate::mesh_processing::{self, Attributes, Normals, DEFAULT_SMOOTHING_ANGLE};
+use crate::pbr::{AlphaMode, PbrMaterial};
+use crate::texture::Texture;
+use crate::mesh::Mesh;
+use crate::vertex::Vertex;
+
+/// modelo glTF 2.0 (.gltf o .glb) con su jerarquía de nodos, materiales y animaciones
+pub struct Gltf {
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    // por cada mesh de glTF sus primitivas, cada una con su material
+    meshes: Vec<Vec<Primitive>>,
+    animations: Vec<Animation>,
+}
+
+pub struct Primitive {
+    pub mesh: Mesh,
+    pub material: Rc<PbrMaterial>,
+}
+
+// transformación local del nodo, la animación cambia estos valores
+#[derive(Clone)]
+struct Node {
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    translation: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+}
+
+struct Animation {
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+struct Channel {
+    node: usize,
+    times: Vec<f32>,
+    keyframes: Keyframes,
+    step: bool,
+}
+
+enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+impl Gltf {
+    /// las texturas y buffers embebidos (base64 o dentro del .glb) se leen aquí también
+    pub fn load(path: &str) -> Result<Self, LoadError> {
+        let (document, buffers, images) = gltf::import(path)
+            .map_err(|error| LoadError::Gltf { path: path.to_string(), error })?;
+        Gltf::from_import(path, document, &buffers, &images)
+    }
+
+    // el documento ya importado; path es solo para los mensajes de error
+    fn from_import(path: &str, document: gltf::Document, buffers: &[gltf::buffer::Data], images: &[gltf::image::Data]) -> Result<Self, LoadError> {
+        let buffer_data = |buffer: gltf::Buffer| buffers.get(buffer.index()).map(|data| &data.0[..]);
+
+        let textures: Vec<Rc<Texture>> = images.iter().map(|image| Rc::new(image_texture(image))).collect();
+        let texture = |info: Option<gltf::texture::Texture>| info.and_then(|t| textures.get(t.source().index()).cloned());
+
+        let materials: Vec<Rc<PbrMaterial>> = document.materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                let [r, g, b, a] = pbr.base_color_factor();
+                let [er, eg, eb] = material.emissive_factor();
+                Rc::new(PbrMaterial {
+                    base_color: Vec4::new(r, g, b, a),
+                    base_color_map: texture(pbr.base_color_texture().map(|info| info.texture())),
+                    metallic: pbr.metallic_factor(),
+                    roughness: pbr.roughness_factor(),
+                    metallic_roughness_map: texture(pbr.metallic_roughness_texture().map(|info| info.texture())),
+                    emissive: Vec3::new(er, eg, eb),
+                    emissive_map: texture(material.emissive_texture().map(|info| info.texture())),
+                    normal_map: texture(material.normal_texture().map(|info| info.texture())),
+                    normal_scale: material.normal_texture().map_or(1.0, |info| info.scale()),
+                    alpha_mode: match material.alpha_mode() {
+                        gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                        gltf::material::AlphaMode::Mask => AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5)),
+                        gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                    },
+                    double_sided: material.double_sided(),
+                })
+            })
+            .collect();
+        // el material por defecto de glTF: blanco, metálico y áspero
+        let default_material = Rc::new(PbrMaterial {
+            base_color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            base_color_map: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_map: None,
+            emissive: Vec3::zeros(),
+            emissive_map: None,
+            normal_map: None,
+            normal_scale: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        });
+
+        let meshes = document.meshes()
+            .map(|mesh| {
+                mesh.primitives()
+                    // puntos y líneas no se dibujan con triángulos
+                    .filter(|primitive| primitive.mode() == Mode::Triangles)
+                    .filter_map(|primitive| {
+                        let reader = primitive.reader(buffer_data);
+                        let positions: Vec<[f32; 3]> = reader.read_positions()?.collect();
+                        let normals: Vec<[f32; 3]> = reader.read_normals().map(|n| n.collect()).unwrap_or_default();
+                        let tangents: Vec<[f32; 4]> = reader.read_tangents().map(|t| t.collect()).unwrap_or_default();
+                        let tex_coords: Vec<[f32; 2]> = reader.read_tex_coords(0).map(|t| t.into_f32().collect()).unwrap_or_default();
+                        let indices: Vec<u32> = match reader.read_indices() {
+                            Some(indices) => indices.into_u32().collect(),
+                            None => (0..positions.len() as u32).collect(),
+                        };
+
+                        let vertices = positions.iter().enumerate()
+                            .map(|(i, &position)| {
+                                let normal = normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]);
+                                let uv = tex_coords.get(i).copied().unwrap_or([0.0, 0.0]);
+                                let mut vertex = Vertex::new(Vec3::from(position), Vec3::from(normal), Vec2::from(uv));
+                                if let Some(&tangent) = tangents.get(i) {
+                                    vertex.tangent = Vec4::from(tangent);
+                                }
+                                vertex
+                            })
+                            .collect();
+                        let attributes = Attributes {
+                            normals: normals.len() == positions.len(),
+                            // las tangentes dependen de las normales, si se generan unas van las otras
+                            tangents: normals.len() == positions.len() && tangents.len() == positions.len(),
+                        };
+                        let material = primitive.material().index()
+                            .and_then(|index| materials.get(index).cloned())
+                            .unwrap_or_else(|| default_material.clone());
+
+                        let normals = Normals::Smooth { max_angle: DEFAULT_SMOOTHING_ANGLE };
+                        let mesh = mesh_processing::prepare(vertices, indices, attributes, normals)
+                            .map_err(|error| LoadError::Mesh { path: format!("{} ({})", path, mesh.name().unwrap_or("sin nombre")), error });
+                        Some(mesh.map(|mesh| Primitive { mesh, material }))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let nodes: Vec<Node> = document.nodes()
+            .map(|node| {
+                let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+                Node {
+                    children: node.children().map(|child| child.index()).collect(),
+                    mesh: node.mesh().map(|mesh| mesh.index()),
+                    translation: Vec3::from(translation),
+                    rotation: Quat::new(w, x, y, z),
+                    scale: Vec3::from(scale),
+                }
+            })
+            .collect();
+
+        // la escena por defecto; si no hay, todos los nodos que no son hijos de otro
+        let roots = match document.default_scene().or_else(|| document.scenes().next()) {
+            Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+            None => (0..nodes.len())
+                .filter(|&index| !nodes.iter().any(|node| node.children.contains(&index)))
+                .collect(),
+        };
+
+        let animations = document.animations()
+            .map(|animation| {
+                let channels: Vec<Channel> = animation.channels()
+                    .filter_map(|channel| {
+                        let reader = channel.reader(buffer_data);
+                        let times: Vec<f32> = reader.read_inputs()?.collect();
+                        let interpolation = channel.sampler().interpolation();
+                        // en cubic spline vienen tangente de entrada, valor y tangente de salida; solo se usa el valor
+                        let values = |count: usize| -> Vec<usize> {
+                            match interpolation {
+                                Interpolation::CubicSpline => (0..count / 3).map(|i| i * 3 + 1).collect(),
+                                _ => (0..count).collect(),
+                            }
+                        };
+                        let keyframes = match reader.read_outputs()? {
+                            ReadOutputs::Translations(outputs) => {
+                                let outputs: Vec<Vec3> = outputs.map(Vec3::from).collect();
+                                Keyframes::Translation(values(outputs.len()).into_iter().map(|i| outputs[i]).collect())
+                            }
+                            ReadOutputs::Rotations(outputs) => {
+                                let outputs: Vec<Quat> = outputs.into_f32().map(|[x, y, z, w]| Quat::new(w, x, y, z)).collect();
+                                Keyframes::Rotation(values(outputs.len()).into_iter().map(|i| outputs[i]).collect())
+                            }
+                            ReadOutputs::Scales(outputs) => {
+                                let outputs: Vec<Vec3> = outputs.map(Vec3::from).collect();
+                                Keyframes::Scale(values(outputs.len()).into_iter().map(|i| outputs[i]).collect())
+                            }
+                            // morph targets no
+                            ReadOutputs::MorphTargetWeights(_) => return None,
+                        };
+
+                        Some(Channel {
+                            node: channel.target().node().index(),
+                            times,
+                            keyframes,
+                            step: interpolation == Interpolation::Step,
+                        })
+                    })
+                    .filter(|channel| !channel.times.is_empty() && channel.times.len() == channel.keyframes.len())
+                    .collect();
+                let duration = channels.iter()
+                    .filter_map(|channel| channel.times.last().copied())
+                    .fold(0.0, f32::max);
+
+                Animation { channels, duration }
+            })
+            .collect();
+
+        Ok(Gltf { nodes, roots, meshes, animations })
+    }
+
+    /// cada primitiva con su matriz de modelo en el segundo dado; las animaciones se repiten
+    pub fn draws(&self, seconds: f32) -> Vec<(Mat4, &Primitive)> {
+        let mut nodes = self.nodes.clone();
+        for animation in &self.animations {
+            let time = if animation.duration > 0.0 { seconds.rem_euclid(animation.duration) } else { 0.0 };
+            for channel in &animation.channels {
+                if let Some(node) = nodes.get_mut(channel.node) {
+                    channel.apply(node, time);
+                }
+            }
+        }
+
+        let mut draws = Vec::new();
+        // la pila va al revés para que los nodos salgan en el orden del archivo, cada padre antes que sus hijos
+        let mut stack: Vec<(usize, Mat4)> = self.roots.iter().rev().map(|&root| (root, Mat4::identity())).collect();
+        // un nodo repetido en la jerarquía no puede colgar el loop
+        let mut budget = nodes.len() * 4;
+        while let Some((index, parent)) = stack.pop() {
+            let Some(node) = nodes.get(index) else { continue };
+            if budget == 0 {
+                break;
+            }
+            budget -= 1;
+
+            let matrix = parent * glm::translation(&node.translation) * glm::quat_to_mat4(&node.rotation) * glm::scaling(&node.scale);
+            if let Some(primitives) = node.mesh.and_then(|mesh| self.meshes.get(mesh)) {
+                draws.extend(primitives.iter().map(|primitive| (matrix, primitive)));
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, matrix)));
+        }
+        draws
+    }
+}
+
+impl Channel {
+    fn apply(&self, node: &mut Node, time: f32) {
+        // la llave anterior y cuánto se avanzó hacia la siguiente
+        let next = self.times.partition_point(|&t| t <= time);
+        let (i, j, t) = if next == 0 {
+            (0, 0, 0.0)
+        } else if next >= self.times.len() {
+            let last = self.times.len() - 1;
+            (last, last, 0.0)
+        } else {
+            let (start, end) = (self.times[next - 1], self.times[next]);
+            let t = if self.step || end <= start { 0.0 } else { (time - start) / (end - start) };
+            (next - 1, next, t)
+        };
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => node.translation = glm::lerp(&values[i], &values[j], t),
+            Keyframes::Rotation(values) => node.rotation = glm::quat_normalize(&glm::quat_slerp(&values[i], &values[j], t)),
+            Keyframes::Scale(values) => node.scale = glm::lerp(&values[i], &values[j], t),
+        }
+    }
+}
+
+impl Keyframes {
+    fn len(&self) -> usize {
+        match self {
+            Keyframes::Translation(values) | Keyframes::Scale(values) => values.len(),
+            Keyframes::Rotation(values) => values.len(),
+        }
+    }
+}
+
+// las imágenes llegan ya decodificadas; se pasan a rgba de 0 a 1
+fn image_texture(image: &gltf::image::Data) -> Texture {
+    use gltf::image::Format;
+    // canales y bytes por canal
+    let (channels, bytes) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes_of: &[u8]| -> f32 {
+        match bytes_of.len() {
+            1 => bytes_of[0] as f32 / 255.0,
+            2 => u16::from_le_bytes([bytes_of[0], bytes_of[1]]) as f32 / 65535.0,
+            _ => f32::from_le_bytes([bytes_of[0], bytes_of[1], bytes_of[2], bytes_of[3]]),
+        }
+    };
+
+    let pixels = image.pixels
+        .chunks_exact(channels * bytes)
+        .map(|pixel| {
+            let value = |c: usize| channel(&pixel[c * bytes..][..bytes]);
+            // un solo canal es gris; con dos el segundo es alpha
+            match channels {
+                1 => Vec4::new(value(0), value(0), value(0), 1.0),
+                2 => Vec4::new(value(0), value(0), value(0), value(1)),
+                3 => Vec4::new(value(0), value(1), value(2), 1.0),
+                _ => Vec4::new(value(0), value(1), value(2), value(3)),
+            }
+        })
+        .collect();
+
+    Texture::from_rgba(image.width as usize, image.height as usize, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // un triángulo, los tiempos 0, 1, 2 y tres traslaciones en x (0, 10, 20)
+    const JSON: &str = r#"{
+        "asset": {"version": "2.0"},
+        "buffers": [{"byteLength": 84}],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+            {"buffer": 0, "byteOffset": 36, "byteLength": 12},
+            {"buffer": 0, "byteOffset": 48, "byteLength": 36}
+        ],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]},
+            {"bufferView": 1, "componentType": 5126, "count": 3, "type": "SCALAR", "min": [0], "max": [2]},
+            {"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC3"}
+        ],
+        "materials": [{"alphaMode": "MASK", "alphaCutoff": 0.3}, {"alphaMode": "MASK"}],
+        "meshes": [
+            {"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]},
+            {"primitives": [{"attributes": {"POSITION": 0}, "material": 1}]}
+        ],
+        "nodes": [
+            {"mesh": 0, "translation": [1, 0, 0], "children": [1, 2]},
+            {"mesh": 1, "translation": [0, 2, 0]},
+            {"mesh": 0, "scale": [3, 3, 3]},
+            {"mesh": 1},
+            {"mesh": 1}
+        ],
+        "animations": [{
+            "samplers": [
+                {"input": 1, "output": 2, "interpolation": "LINEAR"},
+                {"input": 1, "output": 2, "interpolation": "STEP"}
+            ],
+            "channels": [
+                {"sampler": 0, "target": {"node": 3, "path": "translation"}},
+                {"sampler": 1, "target": {"node": 4, "path": "translation"}}
+            ]
+        }],
+        "scenes": [{"nodes": [0, 3, 4]}],
+        "scene": 0
+    }"#;
+
+    // .glb con el json de arriba y el buffer en el chunk binario
+    fn glb() -> Vec<u8> {
+        let floats: [f32; 21] = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+            0.0, 1.0, 2.0,
+            0.0, 0.0, 0.0, 10.0, 0.0, 0.0, 20.0, 0.0, 0.0,
+        ];
+        let bin: Vec<u8> = floats.iter().flat_map(|value| value.to_le_bytes()).collect();
+        let mut json = JSON.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+
+        let mut glb = Vec::new();
+        glb.extend(b"glTF");
+        glb.extend(2u32.to_le_bytes());
+        glb.extend((12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(b"JSON");
+        glb.extend(json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(b"BIN\0");
+        glb.extend(bin);
+        glb
+    }
+
+    fn load() -> Gltf {
+        let (document, buffers, images) = gltf::import_slice(glb()).unwrap();
+        Gltf::from_import("test.glb", document, &buffers, &images).unwrap()
+    }
+
+    fn translation(matrix: &Mat4) -> Vec3 {
+        matrix.column(3).xyz()
+    }
+
+    #[test]
+    fn nodes_come_out_in_file_order_with_parent_transforms() {
+        let gltf = load();
+        let draws = gltf.draws(0.0);
+        assert_eq!(draws.len(), 5);
+
+        let positions: Vec<Vec3> = draws.iter().map(|(matrix, _)| translation(matrix)).collect();
+        assert_eq!(positions[0], Vec3::new(1.0, 0.0, 0.0));
+        // los hijos heredan la traslación del padre
+        assert_eq!(positions[1], Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(positions[2], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(draws[2].0[(0, 0)], 3.0);
+        assert_eq!(positions[3], Vec3::zeros());
+    }
+
+    #[test]
+    fn animations_interpolate_step_and_loop() {
+        let gltf = load();
+        let x = |seconds: f32| {
+            let draws = gltf.draws(seconds);
+            (translation(&draws[3].0).x, translation(&draws[4].0).x)
+        };
+        // lineal a la mitad de la primera llave; step se queda en la anterior
+        assert_eq!(x(0.5), (5.0, 0.0));
+        assert_eq!(x(1.5), (15.0, 10.0));
+        // la animación dura 2 s y vuelve a empezar
+        assert_eq!(x(2.5), (5.0, 0.0));
+    }
+
+    #[test]
+    fn mask_materials_keep_their_cutoff() {
+        let gltf = load();
+        let draws = gltf.draws(0.0);
+        assert_eq!(draws[0].1.material.alpha_mode, AlphaMode::Mask(0.3));
+        // sin alphaCutoff va el 0.5 que pide glTF
+        assert_eq!(draws[1].1.material.alpha_mode, AlphaMode::Mask(0.5));
+    }
+
+    #[test]
+    fn two_channel_images_are_grey_and_alpha() {
+        let image = gltf::image::Data {
+            pixels: vec![255, 64],
+            format: gltf::image::Format::R8G8,
+            width: 1,
+            height: 1,
+        };
+        let texture = Rc::new(image_texture(&image));
+        let texel = texture.sample_rgba(Vec2::new(0.5, 0.5));
+        assert_eq!(texel, Vec4::new(1.0, 1.0, 1.0, 64.0 / 255.0));
+
+        // el alpha de la textura se multiplica con el del factor
+        let material = PbrMaterial {
+            base_color: Vec4::new(1.0, 0.5, 1.0, 0.5),
+            base_color_map: Some(texture),
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_roughness_map: None,
+            emissive: Vec3::zeros(),
+            emissive_map: None,
+            normal_map: None,
+            normal_scale: 1.0,
+            alpha_mode: AlphaMode::Blend,
+            double_sided: false,
+        };
+        assert_eq!(material.base_color_at(Vec2::new(0.5, 0.5)), Vec4::new(1.0, 0.5, 1.0, 32.0 / 255.0));
+    }
+}
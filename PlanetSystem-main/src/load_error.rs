@@ -0,0 +1,30 @@
+use std::fmt;
+use crate::mesh_processing::MeshError;
+
+/// por qué no se pudo cargar un modelo, con el archivo que falló
+#[derive(Debug)]
+pub enum LoadError {
+    Obj { path: String, error: tobj::LoadError },
+    Gltf { path: String, error: gltf::Error },
+    Mesh { path: String, error: MeshError },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Obj { path, error } => write!(f, "no se pudo leer {}: {}", path, error),
+            LoadError::Gltf { path, error } => write!(f, "no se pudo leer {}: {}", path, error),
+            LoadError::Mesh { path, error } => write!(f, "mesh inválido en {}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Obj { error, .. } => Some(error),
+            LoadError::Gltf { error, .. } => Some(error),
+            LoadError::Mesh { error, .. } => Some(error),
+        }
+    }
+}
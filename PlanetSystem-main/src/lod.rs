@@ -0,0 +1,155 @@
+use nalgebra_glm::{Mat4, Vec3};
+use crate::displacement::Displacement;
+use crate::mesh::{self, Mesh};
+
+// bordes (en pixeles de radio) entre bandas: oculto | impostor | mesh 0 | mesh 1 | mesh 2 | mesh 3 | mesh 4
+const LOD_EDGES: [f32; 6] = [0.5, 4.0, 16.0, 48.0, 120.0, 280.0];
+// cuánto hay que pasarse de un borde para cambiar de banda, evita que parpadee
+const HYSTERESIS: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LodLevel {
+    Hidden,
+    Impostor,
+    Mesh(usize),
+}
+
+/// banda actual de un cuerpo, se guarda entre frames
+#[derive(Debug, Clone, Copy)]
+pub struct Lod {
+    band: usize,
+}
+
+impl Lod {
+    pub fn new() -> Self {
+        // arranca en el mesh más fino, el primer frame baja si hace falta
+        Lod { band: LOD_EDGES.len() }
+    }
+
+    /// elige el nivel según el radio proyectado en pantalla
+    pub fn update(&mut self, radius_px: f32) -> LodLevel {
+        // solo se sale de la banda actual si se pasa de sus bordes con margen
+        let lower = if self.band > 0 { LOD_EDGES[self.band - 1] * (1.0 - HYSTERESIS) } else { 0.0 };
+        let upper = LOD_EDGES.get(self.band).map_or(f32::INFINITY, |edge| edge * (1.0 + HYSTERESIS));
+
+        if radius_px < lower || radius_px >= upper {
+            self.band = LOD_EDGES.iter().filter(|&&edge| radius_px >= edge).count();
+        }
+
+        match self.band {
+            0 => LodLevel::Hidden,
+            1 => LodLevel::Impostor,
+            band => LodLevel::Mesh(band - 2),
+        }
+    }
+}
+
+/// bandas de todo lo que cambia de detalle: cada planeta y la luna del rocoso
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLods {
+    pub planets: [Lod; 7],
+    pub moon: Lod,
+}
+
+impl BodyLods {
+    pub fn new() -> Self {
+        BodyLods { planets: [Lod::new(); 7], moon: Lod::new() }
+    }
+}
+
+/// la misma esfera a varias resoluciones más el disco para cuando es puntito
+pub struct SphereLods {
+    pub levels: Vec<Mesh>,
+    pub impostor: Mesh,
+}
+
+impl SphereLods {
+    pub fn new(radius: f32) -> Self {
+        // el último es para ver de cerca el relieve en la silueta
+        let levels = [(8, 4), (16, 8), (32, 16), (48, 24), (96, 48)]
+            .iter()
+            .map(|&(segments, rings)| mesh::uv_sphere(radius, segments, rings))
+            .collect();
+
+        // normales de media esfera para que el disco se sombree como bola
+        let mut impostor = mesh::disc(radius, 12);
+        for vertex in impostor.vertices.iter_mut() {
+            let p = vertex.position / radius;
+            vertex.normal = Vec3::new(p.x, p.y, (1.0 - p.x * p.x - p.y * p.y).max(0.0).sqrt());
+        }
+
+        SphereLods { levels, impostor }
+    }
+
+    pub fn get(&self, level: usize) -> &Mesh {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+
+    /// los mismos niveles con el relieve aplicado; el impostor es un disco y queda igual
+    pub fn displaced(&self, displacement: &Displacement) -> Self {
+        SphereLods {
+            levels: self.levels.iter().map(|level| displacement.displace_mesh(level)).collect(),
+            impostor: self.impostor.clone(),
+        }
+    }
+
+    /// esfera entera para lo que envuelve al cuerpo (la atmósfera); con impostor va la más gruesa
+    pub fn sphere_for(&self, level: LodLevel) -> Option<&Mesh> {
+        match level {
+            LodLevel::Hidden => None,
+            LodLevel::Impostor => Some(self.get(0)),
+            LodLevel::Mesh(level) => Some(self.get(level)),
+        }
+    }
+}
+
+/// radio en pixeles de una esfera vista desde la cámara
+pub fn projected_radius(center: Vec3, radius: f32, camera_position: Vec3, projection_matrix: &Mat4, viewport_height: f32) -> f32 {
+    let distance = (center - camera_position).magnitude();
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+
+    // projection[(1, 1)] es 1 / tan(fov / 2)
+    radius / distance * projection_matrix[(1, 1)] * viewport_height * 0.5
+}
+
+/// matriz de modelo con +Z apuntando a la cámara
+pub fn billboard_matrix(position: Vec3, scale: f32, camera_position: Vec3, up: Vec3) -> Mat4 {
+    let forward = (camera_position - position).normalize();
+    let right = up.cross(&forward).normalize();
+    let up = forward.cross(&right);
+
+    Mat4::new(
+        right.x * scale, up.x * scale, forward.x * scale, position.x,
+        right.y * scale, up.y * scale, forward.y * scale, position.y,
+        right.z * scale, up.z * scale, forward.z * scale, position.z,
+        0.0,             0.0,          0.0,               1.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bands_change_only_past_the_hysteresis_margin() {
+        let mut lod = Lod::new();
+        assert_eq!(lod.update(20.0), LodLevel::Mesh(1));
+        // un poco abajo del borde de 16 px todavía no cambia
+        assert_eq!(lod.update(15.0), LodLevel::Mesh(1));
+        assert_eq!(lod.update(13.0), LodLevel::Mesh(0));
+        // y de vuelta tampoco sube justo en el borde
+        assert_eq!(lod.update(17.0), LodLevel::Mesh(0));
+        assert_eq!(lod.update(19.0), LodLevel::Mesh(1));
+    }
+
+    #[test]
+    fn tiny_bodies_become_impostors_then_hidden() {
+        let mut lod = Lod::new();
+        assert_eq!(lod.update(2.0), LodLevel::Impostor);
+        assert_eq!(lod.update(0.2), LodLevel::Hidden);
+        // un salto grande cruza varias bandas de una
+        assert_eq!(lod.update(500.0), LodLevel::Mesh(4));
+    }
+}
@@ -1,14 +1,16 @@
 
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{Vec3, Vec4, Mat4, look_at, perspective};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use std::f32::consts::PI;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
-use shaders::{fragment_shader, moon_position, vertex_shader, planet_orbit, ShaderType};
-use triangle::triangle;
-use vertex::Vertex;
+use shaders::{fragment_shader, moon_position, planet_orbit, Atmosphere, ShaderType, VertexTransform};
+use triangle::{triangle, edge_function, Rasterizer};
 use camera::Camera;
 use obj_loader::Obj;
-use framebuffer::Framebuffer;
+use gltf_loader::Gltf;
+use load_error::LoadError;
+use framebuffer::{DepthPass, Framebuffer};
 
 mod vertex;
 mod fragments;
@@ -19,11 +21,45 @@ mod camera;
 mod triangle;
 mod obj_loader;
 mod skybox;
-
-
-use skybox::Skybox;
-
-
+mod asteroids;
+mod mesh;
+mod lod;
+mod frustum;
+mod stats;
+mod material;
+mod rings;
+mod config;
+mod post;
+mod vertex_cache;
+mod texture;
+mod phong;
+mod pbr;
+mod gltf_loader;
+mod mesh_processing;
+mod load_error;
+mod normal_mapping;
+mod displacement;
+mod seed;
+mod cubemap;
+mod noise;
+mod nebula;
+mod sky_overlays;
+
+
+use skybox::{Background, Skybox};
+use asteroids::AsteroidBelt;
+use lod::{BodyLods, Lod, LodLevel, SphereLods};
+use frustum::Frustum;
+use stats::{FrameStats, Pass};
+use material::{BlendMode, CullMode, Material};
+use rings::{RingProfile, Rings};
+use mesh::Mesh;
+use vertex_cache::VertexCache;
+use config::{Antialiasing, Config};
+use displacement::Displacement;
+
+
+#[derive(Clone, Copy)]
 pub struct Uniforms {
     model_matrix: Mat4,
     view_matrix: Mat4,
@@ -31,8 +67,20 @@ pub struct Uniforms {
     viewport_matrix: Mat4,
     time: u32,
     debug_mode: u32,
+    camera_position: Vec3,
+    light_position: Vec3,
 }
 
+// dónde queda la estación, cerca de la órbita de la Tierra
+const STATION_POSITION: Vec3 = Vec3::new(-12.0, 3.0, 40.0);
+const STATION_SCALE: f32 = 1.5;
+
+const RING_SCALE: f32 = 2.0;
+// inclinación tipo Saturno para que el sol no quede siempre de canto
+const RING_TILT: f32 = 0.47;
+const RING_INNER_RADIUS: f32 = 1.5;
+const RING_OUTER_RADIUS: f32 = 2.9;
+
 // crea los modelos del SS
 fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4{
     let (sin_x, cos_x) = rotation.x.sin_cos();
@@ -94,45 +142,43 @@ fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     )
 }
 
-fn render_rings(framebuffer: &mut Framebuffer, planet_position: Vec3, uniforms: &Uniforms, vertex_array: &[Vertex]) {
+// los anillos son translúcidos, se encolan para la pasada transparente
+fn render_rings<'a>(transparent_queue: &mut Vec<TransparentDraw<'a>>, planet_position: Vec3, uniforms: &Uniforms, ring_mesh: &'a Mesh, ring_material: &Material) {
     let ring_uniforms = Uniforms {
-        model_matrix: create_model_matrix(planet_position, 2.0, Vec3::new(0.0, 0.0, 0.0)),
-        view_matrix: uniforms.view_matrix,
-        projection_matrix: uniforms.projection_matrix,
-        viewport_matrix: uniforms.viewport_matrix,
-        time: uniforms.time,
-        debug_mode: uniforms.debug_mode,
+        model_matrix: create_model_matrix(planet_position, RING_SCALE, Vec3::new(0.0, 0.0, RING_TILT)),
+        ..*uniforms
     };
-    let ring_shader = ShaderType::Ring;
-    render(framebuffer, &ring_uniforms, vertex_array, &ring_shader);
+    transparent_queue.push(TransparentDraw::new(ring_uniforms, ring_mesh, ring_material.clone()));
 }
 
-// cambio de la luna 
-fn moon_render(framebuffer: &mut Framebuffer, position: Vec3, time: u32, view_matrix: Mat4, projection_matrix: Mat4, viewport_matrix: Mat4, sphere_vertex_arrays: &[Vertex]){
-    let moon_pos = moon_position(time as f32, 1.3);
+// cambio de la luna; tiene su propio nivel de detalle, no se va con el del planeta
+fn moon_render<'a>(opaque: &mut Vec<OpaqueDraw<'a>>, position: Vec3, uniforms: &Uniforms, camera_up: Vec3, lod: &mut Lod, sphere_lods: &'a SphereLods, material: &Material){
+    let moon_pos = moon_position(uniforms.time as f32, 1.3);
+    let scale = 0.5;
     let moon_uniforms = Uniforms {
-        model_matrix: create_model_matrix(position + moon_pos, 0.5, Vec3::new(0.0, 0.0, 0.0)),
-        view_matrix,
-        projection_matrix,
-        viewport_matrix,
-        time,
-        debug_mode: 0,
+        model_matrix: create_model_matrix(position + moon_pos, scale, Vec3::new(0.0, 0.0, 0.0)),
+        ..*uniforms
     };
-    render(framebuffer, &moon_uniforms, sphere_vertex_arrays, &ShaderType::Moon);
+    let radius_px = lod::projected_radius(position + moon_pos, 0.5 * scale, uniforms.camera_position, &uniforms.projection_matrix, WINDOW_HEIGHT as f32);
+    opaque.extend(sphere_draw(lod.update(radius_px), moon_uniforms, scale, camera_up, sphere_lods, material.clone()));
 }
 
-
-fn draw_line(framebuffer: &mut Framebuffer, start: Vec3, end: Vec3, color: u32) {
-    let steps = 100; 
-    for i in 0..steps {
-        let t = i as f32 / (steps - 1) as f32;
-        let x = start.x * (1.0 - t) + end.x * t;
-        let y = start.y * (1.0 - t) + end.y * t ;
-        let z = start.z * (2.0 - t) + end.z * t ; // lo profundoo
-        let x_screen = (framebuffer.width as f32 * (x + 1.0) / 2.0) as usize;
-        let y_screen = (framebuffer.height as f32 * (y + 1.0) / 2.0) as usize ;
-        framebuffer.set_current_color(color);
-        framebuffer.point(x_screen, y_screen, z);
+// un cuerpo redondo según su nivel: la esfera, un disco mirando a la cámara o nada
+fn sphere_draw<'a>(level: LodLevel, uniforms: Uniforms, scale: f32, camera_up: Vec3, sphere_lods: &'a SphereLods, material: Material) -> Option<OpaqueDraw<'a>> {
+    match level {
+        LodLevel::Hidden => None,
+        LodLevel::Impostor => {
+            // ya es un puntito, va un disco mirando a la cámara
+            let position = uniforms.model_matrix.column(3).xyz();
+            let impostor_uniforms = Uniforms {
+                model_matrix: lod::billboard_matrix(position, scale, uniforms.camera_position, camera_up),
+                ..uniforms
+            };
+            // el disco no tiene relieve que mover
+            let flat_material = Material { displacement: None, ..material };
+            Some(OpaqueDraw::new(impostor_uniforms, &sphere_lods.impostor, flat_material))
+        }
+        LodLevel::Mesh(level) => Some(OpaqueDraw::new(uniforms, sphere_lods.get(level), material)),
     }
 }
 
@@ -142,98 +188,341 @@ fn place_ship_front_of_camera(camera: &Camera) -> Vec3 {
     let direction = camera.center - camera.eye; 
     let distance = 10.0; 
     // pone la nave en esa dirección
-    let ship_position = camera.eye + direction.normalize() * distance; 
+    camera.eye + direction.normalize() * distance
+}
 
-    ship_position
+fn render(framebuffer: &mut Framebuffer, stats: &mut FrameStats, uniforms: &Uniforms, mesh: &Mesh, material: &Material) {
+    render_instanced(framebuffer, stats, uniforms, mesh, &[uniforms.model_matrix], material);
 }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], current_shader: &ShaderType) {
-    // para e shader
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms) ;
-        transformed_vertices.push(transformed);
-    }
-
-    // el estado
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3){
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone() ,
-                transformed_vertices[i + 2].clone(),
-            ]);
+// un solo mesh con muchas matrices de modelo (una por instancia)
+fn render_instanced(framebuffer: &mut Framebuffer, stats: &mut FrameStats, uniforms: &Uniforms, mesh: &Mesh, instances: &[Mat4], material: &Material) {
+    // se reusan entre instancias para no pedir memoria cada vez
+    let mut vertex_cache = VertexCache::default();
+    let mut visible: Vec<[u32; 3]> = Vec::with_capacity(mesh.indices.len() / 3);
+    let mut fragments = Vec::new();
+    let frustum = Frustum::from_matrix(&(uniforms.projection_matrix * uniforms.view_matrix));
+
+    // en el pre-pass solo va la profundidad de lo que la escribe
+    let depth_only = framebuffer.depth_pass == DepthPass::PrePass;
+    if depth_only && !material.depth_write {
+        return;
+    }
+
+    for model_matrix in instances {
+        // esfera que envuelve la instancia, si queda fuera de la cámara ni se transforma
+        let scale = (0..3).map(|c| model_matrix.column(c).xyz().magnitude()).fold(0.0, f32::max);
+        let center = model_matrix.column(3).xyz();
+        // el relieve del material puede sacar vértices fuera del radio del mesh
+        let radius = mesh.radius + material.displacement.as_ref().map_or(0.0, |d| d.max_offset());
+        let in_view = frustum.intersects_sphere(center, radius * scale);
+        // los objetos se cuentan una vez por frame, en la pasada de color
+        if !depth_only {
+            if in_view {
+                stats.objects_drawn += 1;
+            } else {
+                stats.objects_culled += 1;
+            }
+        }
+        if !in_view {
+            continue;
+        }
+
+        let instance_uniforms = Uniforms {
+            model_matrix: *model_matrix,
+            ..*uniforms
+        };
+
+        // MVP y matriz de normales una vez por instancia
+        let transform = VertexTransform::new(&instance_uniforms);
+        vertex_cache.reset(mesh.vertices.len());
+
+        // orden de los vértices ya en pantalla: positivo = mira a la cámara
+        visible.clear();
+        for tri in mesh.indices.chunks_exact(3) {
+            let tri = [tri[0], tri[1], tri[2]];
+            for &index in &tri {
+                // para e shader, solo la primera vez que aparece el vértice
+                if vertex_cache.fetch(index, mesh, &transform, material.displacement.as_deref()) {
+                    stats.vertices_shaded += 1;
+                }
+            }
+            // un índice roto deja el triángulo afuera en vez de leer un vértice que no existe
+            let [Some(a), Some(b), Some(c)] = tri.map(|index| vertex_cache.get(index)) else { continue };
+            if !material.cull_mode.culls(edge_function(&a.transformed_position, &b.transformed_position, &c.transformed_position)) {
+                visible.push(tri);
+            }
+        }
+        stats.vertices_referenced += mesh.indices.len() as u32;
+
+        // los visibles tienen sus tres vértices en el cache
+        let vertex = |index: u32| vertex_cache.get(index).expect("vértice de un triángulo visible");
+
+        // lo transparente se mezcla bien solo si va de atrás para adelante
+        if material.is_transparent() {
+            let depth = |tri: &[u32; 3]| tri.iter().map(|&index| vertex(index).transformed_position.z).sum::<f32>();
+            visible.sort_by(|a, b| depth(b).total_cmp(&depth(a)));
+        }
+
+        // ras, y cada triángulo se sombrea enseguida para que el siguiente ya vea su profundidad
+        for &[i1, i2, i3] in &visible {
+            let raster_start = Instant::now();
+            fragments.clear();
+            triangle(vertex(i1), vertex(i2), vertex(i3), framebuffer, &mut fragments);
+            stats.raster_ms += raster_start.elapsed().as_secs_f32() * 1000.0;
+            stats.fragments_rasterized += fragments.len() as u32;
+
+            for fragment in &fragments {
+                // los recortes de Mask no escriben profundidad, ni en el pre-pass
+                if shaders::discards(fragment, &material.shader) {
+                    stats.fragments_rejected += 1;
+                    continue;
+                }
+                // early-z: lo tapado no llega al shader
+                let coverage = framebuffer.depth_test(fragment, material.depth_write);
+                if coverage == 0 {
+                    stats.fragments_rejected += 1;
+                    continue;
+                }
+                if depth_only {
+                    continue;
+                }
+
+                // se pone el como fragment del shader, una vez por pixel aunque haya MSAA
+                let shaded_color = fragment_shader(fragment, &instance_uniforms, &material.shader);
+                framebuffer.blend_samples(fragment, coverage, shaded_color, material.blend_mode);
+                stats.fragments_shaded += 1;
+            }
         }
     }
 
-    // ras
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        let normal = (tri[1].position - tri[0].position).cross(&(tri[2].position - tri[0].position));
-        let view_dir = tri[0].position - Vec3::new(0.0, 0.0, 0.0);
-    
-        if normal.dot(&view_dir) < 0.0{
-            continue ; }
-    
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
-    }
-
-    
-    for fragment in fragments{
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            // se pone el como fragment del shader
-            let shaded_color = fragment_shader(&fragment, &uniforms, current_shader);
-            let color = shaded_color.to_hex() ;
-            framebuffer.set_current_color(color) ;
-            framebuffer.point(x, y, fragment.depth) ;
+}
+
+// un mesh opaco con sus instancias (una sola si no es instanciado)
+struct OpaqueDraw<'a> {
+    uniforms: Uniforms,
+    mesh: &'a Mesh,
+    instances: Vec<Mat4>,
+    material: Material,
+}
+
+impl<'a> OpaqueDraw<'a> {
+    fn new(uniforms: Uniforms, mesh: &'a Mesh, material: Material) -> Self {
+        OpaqueDraw { uniforms, mesh, instances: vec![uniforms.model_matrix], material }
+    }
+}
+
+// lo transparente se guarda y se dibuja después de todo lo opaco
+struct TransparentDraw<'a> {
+    distance: f32,
+    uniforms: Uniforms,
+    mesh: &'a Mesh,
+    material: Material,
+}
+
+impl<'a> TransparentDraw<'a> {
+    fn new(uniforms: Uniforms, mesh: &'a Mesh, material: Material) -> Self {
+        let distance = (uniforms.model_matrix.column(3).xyz() - uniforms.camera_position).magnitude();
+        TransparentDraw { distance, uniforms, mesh, material }
+    }
+}
+
+// de atrás para adelante según la distancia a la cámara
+fn render_transparent(framebuffer: &mut Framebuffer, stats: &mut FrameStats, queue: &mut Vec<TransparentDraw>) {
+    queue.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+    for draw in queue.drain(..) {
+        render(framebuffer, stats, &draw.uniforms, draw.mesh, &draw.material);
+    }
+}
+
+const WINDOW_WIDTH: usize = 800;
+const WINDOW_HEIGHT: usize = 600;
+
+// cuánto se genera al cargar la escena; las pruebas usan una más chica
+struct SceneDetail {
+    // lado en texels de cada cara del cielo procedural
+    sky_face_size: usize,
+    asteroid_count: usize,
+}
+
+const FULL_DETAIL: SceneDetail = SceneDetail { sky_face_size: 256, asteroid_count: 1500 };
+
+// todo lo que se carga una vez al arrancar
+struct Scene {
+    // de la que salen las de cada cosa generada, con la misma se arma la misma escena
+    seed: u64,
+    sphere_lods: SphereLods,
+    ring_mesh: Mesh,
+    ring_material: Material,
+    // una parte por material del .mtl
+    ship_parts: Vec<(Mesh, Material)>,
+    // estación en glTF, opcional: sin el archivo la escena sigue igual
+    station: Option<Gltf>,
+    engine_glow_mesh: Mesh,
+    engine_glow_material: Material,
+    skybox: Skybox,
+    asteroid_belt: AsteroidBelt,
+    asteroid_material: Material,
+    // las esferas de los cuerpos rocosos con el relieve ya aplicado, la geometría cambia y no solo el color
+    volcanic_lods: SphereLods,
+    rocky_lods: SphereLods,
+    moon_lods: SphereLods,
+    moon_material: Material,
+}
+
+impl Scene {
+    fn load(seed: u64, detail: &SceneDetail) -> Result<Self, LoadError> {
+        // aquí se ponene los 3d / la sphere y los rings ya no dependen de los .obj
+        let sphere_lods = SphereLods::new(0.5);
+        // el relieve se hornea una vez por nivel, no en cada frame
+        let volcanic_terrain = Displacement::from_image_or_noise("models/volcanic_height.png", seed::derive(seed, "volcanic terrain") as u32, 0.015);
+        let rocky_terrain = Displacement::from_image_or_noise("models/rocky_height.png", seed::derive(seed, "rocky terrain") as u32, 0.02);
+        let volcanic_lods = sphere_lods.displaced(&volcanic_terrain);
+        let rocky_lods = sphere_lods.displaced(&rocky_terrain);
+        // los cráteres del shader también hunden la superficie, el bump queda para el detalle
+        let moon_lods = sphere_lods.displaced(&Displacement::procedural(|p| shaders::moon_height(p) / 0.06, 0.06));
+        let ring_mesh = mesh::annulus(RING_INNER_RADIUS, RING_OUTER_RADIUS, 128);
+        // si hay un perfil en imagen se usa, si no el procedural
+        let ring_profile = RingProfile::from_image("models/ring_profile.png")
+            .unwrap_or_else(|_| RingProfile::procedural(512));
+        let ring_material = Material::blended(
+            ShaderType::Ring(Rings {
+                profile: Rc::new(ring_profile),
+                inner_radius: RING_INNER_RADIUS,
+                outer_radius: RING_OUTER_RADIUS,
+                // radio del planeta (0.5 * 4.5) pasado al espacio del anillo
+                planet_radius: 0.5 * 4.5 / RING_SCALE,
+            }),
+            BlendMode::Alpha,
+        );
+
+        // cambios 
+        let ship_loader = Obj::load("models/ship.obj")?;
+        // sin material en el .mtl se queda el shader morado de antes
+        let ship_parts = ship_loader.get_submeshes().into_iter()
+            .map(|part| {
+                let material = match part.material {
+                    Some(phong) if phong.is_transparent() => Material::blended(ShaderType::BlinnPhong(phong), BlendMode::Alpha),
+                    Some(phong) => Material::new(ShaderType::BlinnPhong(phong)),
+                    None => Material::new(ShaderType::Ship),
+                };
+                (part.mesh, material)
+            })
+            .collect();
+
+        let station = match Gltf::load("models/station.gltf") {
+            Ok(station) => Some(station),
+            Err(error) => {
+                eprintln!("{}", error);
+                None
+            }
+        };
+
+        // con catálogo el cielo de verdad, si no estrellas al azar siempre iguales
+        let mut skybox = Skybox::from_catalogue("models/hygdata.csv", 100.0)
+            .unwrap_or_else(|_| Skybox::random(1000, 100.0, seed::derive(seed, "stars")))
+            // imágenes si hay, si no el cielo procedural de la semilla
+            .with_background(Some(Background::load("models/skybox").unwrap_or_else(|| {
+                Background::Cubemap(nebula::bake(seed::derive(seed, "nebula"), detail.sky_face_size))
+            })));
+        // las figuras son opcionales; sin el archivo no se avisa
+        match skybox.load_constellations("models/constellationship.fab") {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => eprintln!("constelaciones: {}", error),
+            _ => {}
         }
+
+        Ok(Scene {
+            seed,
+            sphere_lods,
+            ring_mesh,
+            ring_material,
+            ship_parts,
+            station,
+            engine_glow_mesh: mesh::quad(1.0, 1.0),
+            engine_glow_material: Material::blended(ShaderType::EngineGlow, BlendMode::Additive),
+            skybox,
+            // cinturón entre el rocoso (30) y el gaseoso (40)
+            asteroid_belt: AsteroidBelt::new(detail.asteroid_count, 33.0, 37.0, seed::derive(seed, "asteroids")),
+            asteroid_material: Material::new(ShaderType::Asteroid),
+            volcanic_lods,
+            rocky_lods,
+            moon_lods,
+            moon_material: Material::new(ShaderType::Moon),
+        })
     }
+}
 
+// para la cam
+fn initial_camera() -> Camera {
+    Camera::new(
+        Vec3::new(0.0, 0.0, 70.0),
+        Vec3::new(0.0, 0.0, 0.0) , 
+        Vec3::new(0.0, 1.0, 0.0)
+    )
 }
 
 // MAINNNN
 fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+    let config = match Config::from_args() {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n{}", message, config::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // antes de abrir la ventana, si un modelo no sirve se avisa y se sale
+    let mut scene = match Scene::load(config.seed, &FULL_DETAIL) {
+        Ok(scene) => scene,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-    let mut window = Window::new(
+    if let Some(frames) = config.bench_frames {
+        run_benchmark(&config, &scene, frames);
+        return;
+    }
+
+    let window_width = WINDOW_WIDTH;
+    let window_height = WINDOW_HEIGHT;
+    // con SSAA se renderiza más grande y se achica al final
+    let ssaa_factor = config.antialiasing.ssaa_factor();
+    let framebuffer_width = window_width * ssaa_factor;
+    let framebuffer_height = window_height * ssaa_factor;
+
+    let mut framebuffer = Framebuffer::with_samples(framebuffer_width, framebuffer_height, config.antialiasing.msaa_samples());
+    framebuffer.rasterizer = config.rasterizer;
+    let mut window = match Window::new(
         "Planet System - Belén",
         window_width,
         window_height,
         WindowOptions::default(),
-    )
-    .unwrap();
+    ) {
+        Ok(window) => window,
+        Err(error) => {
+            eprintln!("no se pudo abrir la ventana: {}", error);
+            std::process::exit(1);
+        }
+    };
 
     window.set_position(900, 500);
     window.update();
 
     framebuffer.set_background_color(0x000000);
 
-    // para la cam
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, 70.0),
-        Vec3::new(0.0, 0.0, 0.0) , 
-        Vec3::new(0.0, 1.0, 0.0)
-    );
+    let mut camera = initial_camera();
 
-    // aquí se ponene los 3d / para los rings y la sphere 
-    let sphere_loader = Obj::load("models/sphere.obj").expect("Failed sphere (obj)");
-    let sphere_vertex_arrays = sphere_loader.get_vertex_array();
-    let ring_loader = Obj::load("models/ring.obj").expect("Failed ring - obj");
-    let ring_vertex_array = ring_loader.get_vertex_array();
     let mut time = 0 ;
     let mut last_frame = Instant::now();
 
-    // cambios 
-    let ship_loader = Obj::load("models/ship.obj").expect("Failed to load ring obj");
-    let ship_vertex_array = ship_loader.get_vertex_array();
-    let skybox = Skybox::new(1000, 100.0);
+    // nivel de detalle de cada planeta y de la luna, se guarda entre frames por la histéresis
+    let mut body_lods = BodyLods::new();
+
+    // contadores que se pintan en la esquina (F1 para esconderlos)
+    let mut stats = FrameStats::default();
+    let mut show_stats = true;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
@@ -244,22 +533,194 @@ fn main() {
         if now - last_frame < Duration::from_millis(16) {
             continue; // 60 FPS porque la compu es lenta
         }
+        let frame_ms = (now - last_frame).as_secs_f32() * 1000.0;
         last_frame = now;
         
         time += 1;
 
         handle_input(&window, &mut camera);
+        if window.is_key_pressed(Key::F1, KeyRepeat::No) {
+            show_stats = !show_stats;
+        }
+        // ayudas del cielo para clases
+        let overlays = &mut scene.skybox.overlays;
+        if window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            overlays.constellations = !overlays.constellations;
+        }
+        if window.is_key_pressed(Key::F3, KeyRepeat::No) {
+            overlays.grid = !overlays.grid;
+        }
+        if window.is_key_pressed(Key::F4, KeyRepeat::No) {
+            overlays.equator = !overlays.equator;
+        }
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            overlays.ecliptic = !overlays.ecliptic;
+        }
 
         framebuffer.clear();
+        stats.reset();
+        stats.frame_ms = frame_ms;
+
+        render_frame_passes(&mut framebuffer, &mut stats, &scene, &camera, time, &mut body_lods, config.depth_prepass);
+
+        let mut frame = post_process(&framebuffer, &mut stats, config.antialiasing);
+
+        if show_stats {
+            let mut lines = stats.lines();
+            lines.push(format!("AA {}", config.antialiasing));
+            lines.push(format!("SEED {}", scene.seed));
+            if config.depth_prepass {
+                lines.push("DEPTH PRE-PASS".to_string());
+            }
+            stats::draw_overlay(&mut frame, window_width, window_height, &lines, 0x00FF00);
+        }
+
+        window
+            .update_with_buffer(&frame, window_width, window_height)
+            .unwrap();
+    }
+}
+
+// sin ventana: la misma escena con cada rasterizador, desde la cámara inicial
+fn run_benchmark(config: &Config, scene: &Scene, frames: u32) {
+    let ssaa_factor = config.antialiasing.ssaa_factor();
+    let mut times = Vec::new();
+
+    for rasterizer in [Rasterizer::Scalar, Rasterizer::Simd] {
+        let mut framebuffer = Framebuffer::with_samples(
+            WINDOW_WIDTH * ssaa_factor,
+            WINDOW_HEIGHT * ssaa_factor,
+            config.antialiasing.msaa_samples(),
+        );
+        framebuffer.rasterizer = rasterizer;
+        let camera = initial_camera();
+        let mut body_lods = BodyLods::new();
+        let mut stats = FrameStats::default();
+
+        let mut raster_ms = 0.0;
+        // promedio de cada pasada, se arma en un FrameStats para imprimirlo igual que el HUD
+        let mut passes = FrameStats::default();
+        let start = Instant::now();
+        for time in 1..=frames {
+            framebuffer.clear();
+            stats.reset();
+            render_frame_passes(&mut framebuffer, &mut stats, scene, &camera, time, &mut body_lods, config.depth_prepass);
+            // la imagen no se muestra pero el post cuenta en el frame igual que con ventana
+            post_process(&framebuffer, &mut stats, config.antialiasing);
+            raster_ms += stats.raster_ms;
+            for (total, ms) in passes.pass_ms.iter_mut().zip(stats.pass_ms) {
+                *total += ms;
+            }
+        }
+        let frames = frames.max(1) as f32;
+        let frame_ms = start.elapsed().as_secs_f32() * 1000.0 / frames;
+        let raster_ms = raster_ms / frames;
+        for total in passes.pass_ms.iter_mut() {
+            *total /= frames;
+        }
+
+        println!(
+            "{:?}: {:.2} ms por frame, {:.2} ms rasterizando, overdraw {:.2} (AA {}, pre-pass {}, semilla {})",
+            rasterizer, frame_ms, raster_ms, stats.overdraw(), config.antialiasing, config.depth_prepass, scene.seed
+        );
+        println!("  pasadas (ms): {}", passes.passes_line());
+        times.push((frame_ms, raster_ms));
+    }
+
+    let ((scalar_frame, scalar_raster), (simd_frame, simd_raster)) = (times[0], times[1]);
+    println!(
+        "SIMD: rasterizado {:.2}x más rápido, frame completo {:.2}x",
+        scalar_raster / simd_raster,
+        scalar_frame / simd_frame
+    );
+}
+
+// de muestras/pixeles grandes a la imagen de la ventana
+fn post_process(framebuffer: &Framebuffer, stats: &mut FrameStats, antialiasing: Antialiasing) -> Vec<u32> {
+    let start = Instant::now();
+    let ssaa_factor = antialiasing.ssaa_factor();
+    let mut frame = framebuffer.resolve();
+    if ssaa_factor > 1 {
+        frame = post::downsample(&frame, framebuffer.width, framebuffer.height, ssaa_factor);
+    }
+    if antialiasing == Antialiasing::Fxaa {
+        frame = post::fxaa(&frame, framebuffer.width / ssaa_factor, framebuffer.height / ssaa_factor);
+    }
+    stats.end_pass(Pass::Post, start);
+    frame
+}
+
+// un frame: primero se arma qué se dibuja y después cada pasada corre una sola vez; con pre-pass
+// antes va solo la profundidad de lo opaco, así cada pixel opaco se sombrea una sola vez
+fn render_frame_passes(framebuffer: &mut Framebuffer, stats: &mut FrameStats, scene: &Scene, camera: &Camera, time: u32, body_lods: &mut BodyLods, depth_prepass: bool) {
+    stats.pixels = (framebuffer.width * framebuffer.height) as u32;
+    let mut draws = FrameDraws::build(framebuffer, scene, camera, time, body_lods);
+
+    if depth_prepass {
+        framebuffer.depth_pass = DepthPass::PrePass;
+        let start = Instant::now();
+        draws.render_opaque(framebuffer, stats);
+        stats.end_pass(Pass::Opaque, start);
+        framebuffer.depth_pass = DepthPass::AfterPrePass;
+    } else {
+        framebuffer.depth_pass = DepthPass::Single;
+    }
+
+    let start = Instant::now();
+    draws.render_opaque(framebuffer, stats);
+    stats.end_pass(Pass::Opaque, start);
 
-        // matrices
-        let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-        let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
+    // el cielo después de lo opaco, solo donde no quedó nada (ver Pass)
+    let start = Instant::now();
+    scene.skybox.render_sb(framebuffer, &draws.camera_uniforms, camera.eye);
+    stats.end_pass(Pass::Sky, start);
+
+    let start = Instant::now();
+    render_transparent(framebuffer, stats, &mut draws.rings);
+    stats.end_pass(Pass::Rings, start);
+
+    let start = Instant::now();
+    render_transparent(framebuffer, stats, &mut draws.transparent);
+    stats.end_pass(Pass::Transparent, start);
+
+    let start = Instant::now();
+    for &(radius, speed) in &draws.orbits {
+        render_orbit_trail(framebuffer, &draws.camera_uniforms, radius, speed);
+    }
+    stats.end_pass(Pass::Overlays, start);
+}
+
+// lo que va en cada pasada de un frame
+struct FrameDraws<'a> {
+    // vista, proyección y luz del frame con matriz de modelo identidad
+    camera_uniforms: Uniforms,
+    opaque: Vec<OpaqueDraw<'a>>,
+    rings: Vec<TransparentDraw<'a>>,
+    transparent: Vec<TransparentDraw<'a>>,
+    // radio y velocidad de cada órbita para las estelas
+    orbits: Vec<(f32, f32)>,
+}
+
+impl<'a> FrameDraws<'a> {
+    // los niveles de detalle se actualizan acá, una vez por frame, por la histéresis
+    fn build(framebuffer: &Framebuffer, scene: &'a Scene, camera: &Camera, time: u32, body_lods: &mut BodyLods) -> Self {
+        let projection_matrix = create_perspective_matrix(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
+        let camera_uniforms = Uniforms {
+            model_matrix: Mat4::identity(),
+            view_matrix: create_view_matrix(camera.eye, camera.center, camera.up),
+            projection_matrix,
+            viewport_matrix: create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32),
+            time,
+            debug_mode: 0,
+            camera_position: camera.eye,
+            // la luz sale del sol, que está en el origen
+            light_position: Vec3::new(0.0, 0.0, 0.0),
+        };
+        let mut draws = FrameDraws { camera_uniforms, opaque: Vec::new(), rings: Vec::new(), transparent: Vec::new(), orbits: Vec::new() };
 
         // cambios para colocar la nave
         // pone la nave un poco enfrente de la cámara para que sea visible 
-        let ship_position = place_ship_front_of_camera(&camera);
+        let ship_position = place_ship_front_of_camera(camera);
 
         // rotación de 90 en Y
         let rotation = Mat4::new_rotation(Vec3::new(0.0, 90.0_f32.to_radians(), 0.0));
@@ -268,81 +729,163 @@ fn main() {
         // matriz de modelo para la nave 
         let ship_uniforms = Uniforms {
             model_matrix: create_model_matrix(ship_position + Vec3::new(0.0, -5.0, 0.0), scale, Vec3::new(0.0, 0.0, 0.0)) * rotation,
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            debug_mode: 0,
+            ..camera_uniforms
         };
 
-        // se renderiza la nave 
-        render(&mut framebuffer, &ship_uniforms, &ship_vertex_array, &ShaderType::Ship);
+        // la nave, las partes transparentes van con el resto de la pasada transparente
+        for (mesh, material) in &scene.ship_parts {
+            draws.push(ship_uniforms, mesh, material.clone());
+        }
+
+        // la estación con sus nodos animados; time va en frames, la animación en segundos
+        if let Some(station) = &scene.station {
+            let placement = create_model_matrix(STATION_POSITION, STATION_SCALE, Vec3::new(0.4, 0.3, 0.0));
+            for (node_matrix, primitive) in station.draws(time as f32 / 60.0) {
+                let uniforms = Uniforms {
+                    model_matrix: placement * node_matrix,
+                    ..camera_uniforms
+                };
+                draws.push(uniforms, &primitive.mesh, Material::from_pbr(primitive.material.clone()));
+            }
+        }
+
+        // brillo del motor en la cola de la nave (el -X del modelo)
+        let engine_position = ship_uniforms.model_matrix * Vec4::new(-7.2, 0.3, 0.0, 1.0);
+        let engine_glow_uniforms = Uniforms {
+            model_matrix: lod::billboard_matrix(engine_position.xyz(), 1.5, camera.eye, camera.up),
+            ..ship_uniforms
+        };
+        draws.transparent.push(TransparentDraw::new(engine_glow_uniforms, &scene.engine_glow_mesh, scene.engine_glow_material.clone()));
 
-      
         // renderizar cada unos de los planets según la distancis 
-        let planet_positions = vec![
-            (Vec3::new(0.0, 0.0, 0.0), ShaderType::Sun, 10.0),
-            (Vec3::new(10.0, 0.0, 0.0), ShaderType::VolcanicPlanet, 2.8),
-            (Vec3::new(20.0, 0.0, 0.0), ShaderType::Earth, 1.5), 
-            (Vec3::new(30.0, 0.0, 0.0), ShaderType::RockyPlanet, 2.6),
-            (Vec3::new(40.0, 0.0, 0.0), ShaderType::GasPlanet, 5.0),
-            (Vec3::new(50.0, 0.0, 0.0), ShaderType::RingPlanet, 4.5),
-            (Vec3::new(60.0, 0.0, 0.0), ShaderType::IcyPlanet, 1.8),
+        let planet_positions = [
+            (Vec3::new(0.0, 0.0, 0.0), Material::new(ShaderType::Sun), 10.0, None, &scene.sphere_lods),
+            (Vec3::new(10.0, 0.0, 0.0), Material::new(ShaderType::VolcanicPlanet), 2.8, None, &scene.volcanic_lods),
+            (Vec3::new(20.0, 0.0, 0.0), Material::new(ShaderType::Earth), 1.5, Some(Atmosphere::earth_like(1.0)), &scene.sphere_lods), 
+            (Vec3::new(30.0, 0.0, 0.0), Material::new(ShaderType::RockyPlanet), 2.6, None, &scene.rocky_lods),
+            (Vec3::new(40.0, 0.0, 0.0), Material::new(ShaderType::GasPlanet), 5.0, None, &scene.sphere_lods),
+            (Vec3::new(50.0, 0.0, 0.0), Material::new(ShaderType::RingPlanet), 4.5, None, &scene.sphere_lods),
+            (Vec3::new(60.0, 0.0, 0.0), Material::new(ShaderType::IcyPlanet), 1.8, Some(Atmosphere::earth_like(0.35)), &scene.sphere_lods),
         ];    
 
-        let mut orbits: Vec<Vec<Vec3>> = vec![vec![]; planet_positions.len()];  
-
-        for (i, (base_position, shader, scale)) in planet_positions.iter().enumerate() {           
-            
+        for (i, (base_position, material, scale, atmosphere, sphere_lods)) in planet_positions.into_iter().enumerate() {
             let orbital_speed = 0.01 + i as f32 * 0.03; 
             let orbital_radius = base_position.x; // posición inicial es el radio de orbutra 
             let orbital_position = planet_orbit(time as f32, orbital_radius, orbital_speed);
-
-            if orbits[i].len() > 1000 {
-                orbits[i].remove(0); 
+            // el sol no tiene órbita que dibujar
+            if orbital_radius > 0.0 {
+                draws.orbits.push((orbital_radius, orbital_speed));
             }
-            orbits[i].push(orbital_position);
 
             let uniforms = Uniforms {
-                model_matrix: create_model_matrix(orbital_position, *scale, Vec3::new(0.0, 0.0, 0.0)),
-                view_matrix,
-                projection_matrix,
-                viewport_matrix,
-                time,
-                debug_mode: 0,
+                model_matrix: create_model_matrix(orbital_position, scale, Vec3::new(0.0, 0.0, 0.0)),
+                ..camera_uniforms
             };
 
-            // render del skybox
-            skybox.render_sb(&mut framebuffer, &uniforms, camera.eye);
-            
-    
-            // render   orbitas
-            for (_i, orbit) in orbits.iter().enumerate() {
-                let color = 0xFF0000;
-                for j in 0..orbit.len().saturating_sub(1) {
-                    draw_line(&mut framebuffer, orbit[j], orbit[j + 1], color);
+            // resolución según lo grande que se ve en pantalla
+            let radius_px = lod::projected_radius(orbital_position, 0.5 * scale, camera.eye, &projection_matrix, WINDOW_HEIGHT as f32);
+            let level = body_lods.planets[i].update(radius_px);
+
+            // la atmósfera es una esfera un poco más grande que se suma en la pasada transparente
+            if let (Some(atmosphere), Some(shell_mesh)) = (atmosphere, scene.sphere_lods.sphere_for(level)) {
+                let shell_scale = scale * atmosphere.shell_scale;
+                let mut atmosphere_material = Material::blended(ShaderType::Atmosphere(atmosphere), BlendMode::Additive);
+                // desde adentro de la cáscara solo se ven las caras de atrás
+                if (camera.eye - orbital_position).magnitude() < 0.5 * shell_scale {
+                    atmosphere_material.cull_mode = CullMode::Front;
                 }
-            }            
-
-            // render planets
-            render(&mut framebuffer, &uniforms, &sphere_vertex_arrays, &shader);
+                let atmosphere_uniforms = Uniforms {
+                    model_matrix: create_model_matrix(orbital_position, shell_scale, Vec3::new(0.0, 0.0, 0.0)),
+                    ..uniforms
+                };
+                draws.transparent.push(TransparentDraw::new(atmosphere_uniforms, shell_mesh, atmosphere_material));
+            }
 
-            // render rings  
-            match shader {
+            // render rings; los anillos son más grandes que el planeta, no se esconden con él
+            match material.shader {
                 ShaderType::RingPlanet => {
-                    render_rings(&mut framebuffer, orbital_position, &uniforms, &ring_vertex_array);
+                    render_rings(&mut draws.rings, orbital_position, &uniforms, &scene.ring_mesh, &scene.ring_material);
                 }
                 ShaderType::RockyPlanet => {
-                    moon_render(&mut framebuffer, orbital_position, time, view_matrix, projection_matrix, viewport_matrix, &sphere_vertex_arrays);
+                    moon_render(&mut draws.opaque, orbital_position, &uniforms, camera.up, &mut body_lods.moon, &scene.moon_lods, &scene.moon_material);
                 }
                 _ => {}
             }
-            
+
+            // render planets
+            draws.opaque.extend(sphere_draw(level, uniforms, scale, camera.up, sphere_lods, material));
         }
 
-        window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
-            .unwrap();
+        // el cinturón, todas las rocas en una sola llamada
+        draws.opaque.push(OpaqueDraw {
+            uniforms: camera_uniforms,
+            mesh: &scene.asteroid_belt.mesh,
+            instances: scene.asteroid_belt.instances(time as f32),
+            material: scene.asteroid_material.clone(),
+        });
+
+        draws
+    }
+
+    // a la pasada que le toca según el material
+    fn push(&mut self, uniforms: Uniforms, mesh: &'a Mesh, material: Material) {
+        if material.is_transparent() {
+            self.transparent.push(TransparentDraw::new(uniforms, mesh, material));
+        } else {
+            self.opaque.push(OpaqueDraw::new(uniforms, mesh, material));
+        }
+    }
+
+    fn render_opaque(&self, framebuffer: &mut Framebuffer, stats: &mut FrameStats) {
+        for draw in &self.opaque {
+            render_instanced(framebuffer, stats, &draw.uniforms, draw.mesh, &draw.instances, &draw.material);
+        }
+    }
+}
+
+// cuántos frames para atrás llega la estela, como mucho una vuelta entera
+const ORBIT_TRAIL_FRAMES: u32 = 1000;
+const ORBIT_COLOR: u32 = 0xFF0000;
+
+// por dónde pasó el planeta en los últimos frames, con la profundidad de la escena
+fn render_orbit_trail(framebuffer: &mut Framebuffer, uniforms: &Uniforms, radius: f32, speed: f32) {
+    let full_turn = (2.0 * PI / speed).ceil() as u32;
+    let frames = uniforms.time.min(ORBIT_TRAIL_FRAMES).min(full_turn);
+    let clip = uniforms.projection_matrix * uniforms.view_matrix;
+    let project = |frame: u32| {
+        let position = planet_orbit(frame as f32, radius, speed);
+        let clip_position = clip * Vec4::new(position.x, position.y, position.z, 1.0);
+        // detrás de la cámara no se proyecta
+        if clip_position.w < 0.1 {
+            return None;
+        }
+        let ndc = clip_position / clip_position.w;
+        Some((uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0)).xyz())
+    };
+
+    let mut previous = project(uniforms.time);
+    for frame in (uniforms.time - frames..uniforms.time).rev() {
+        let current = project(frame);
+        if let (Some(start), Some(end)) = (previous, current) {
+            draw_line(framebuffer, start, end, ORBIT_COLOR);
+        }
+        previous = current;
+    }
+}
+
+// entre dos puntos ya en pantalla (z es la profundidad)
+fn draw_line(framebuffer: &mut Framebuffer, start: Vec3, end: Vec3, color: u32) {
+    let steps = (end.xy() - start.xy()).abs().max().ceil() as usize;
+    // muy cerca de la cámara un tramo cruza la pantalla varias veces, no vale la pena
+    if steps > 4 * (framebuffer.width + framebuffer.height) {
+        return;
+    }
+    framebuffer.set_current_color(color);
+    for i in 0..=steps {
+        let p = start.lerp(&end, i as f32 / steps.max(1) as f32);
+        if p.x >= 0.0 && p.y >= 0.0 {
+            framebuffer.point(p.x as usize, p.y as usize, p.z);
+        }
     }
 }
 
@@ -399,3 +942,26 @@ fn handle_input(window: &Window, camera: &mut Camera) {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // un frame chico desde la cámara inicial
+    fn render_with_seed(seed: u64) -> Vec<u32> {
+        // sin optimizar, el cielo grande y las 1500 rocas se llevan casi todo el tiempo
+        let scene = Scene::load(seed, &SceneDetail { sky_face_size: 16, asteroid_count: 50 }).unwrap();
+        let mut framebuffer = Framebuffer::with_samples(WINDOW_WIDTH / 4, WINDOW_HEIGHT / 4, 1);
+        let mut stats = FrameStats::default();
+        let mut body_lods = BodyLods::new();
+        render_frame_passes(&mut framebuffer, &mut stats, &scene, &initial_camera(), 120, &mut body_lods, false);
+        framebuffer.resolve()
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_frame() {
+        let frame = render_with_seed(7);
+        assert!(frame == render_with_seed(7));
+        assert!(frame != render_with_seed(8));
+    }
+}
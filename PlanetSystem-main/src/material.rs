@@ -0,0 +1,149 @@
+use std::rc::Rc;
+use crate::displacement::Displacement;
+use crate::pbr::{AlphaMode, PbrMaterial};
+use crate::shaders::ShaderType;
+
+/// qué caras se descartan según su orden en pantalla
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CullMode {
+    Back,
+    Front,
+    None,
+}
+
+impl CullMode {
+    /// area es el doble del área con signo en pantalla, positiva si la cara mira a la cámara
+    pub fn culls(self, area: f32) -> bool {
+        match self {
+            CullMode::Back => area <= 0.0,
+            CullMode::Front => area >= 0.0,
+            CullMode::None => area == 0.0,
+        }
+    }
+}
+
+/// cómo se junta el color nuevo con el que ya está en el buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    Additive,
+    // oscurece lo de atrás (dst * src); ningún material de la escena lo usa todavía
+    #[allow(dead_code)]
+    Multiply,
+}
+
+/// shader más el estado de render con el que se dibuja
+#[derive(Clone)]
+pub struct Material {
+    pub shader: ShaderType,
+    pub cull_mode: CullMode,
+    pub blend_mode: BlendMode,
+    pub depth_write: bool,
+    // relieve que se aplica en el vertex stage; lo horneado al cargar no lo necesita
+    pub displacement: Option<Rc<Displacement>>,
+}
+
+impl Material {
+    pub fn new(shader: ShaderType) -> Self {
+        // los anillos y la nave son delgados, se ven de los dos lados
+        let cull_mode = match shader {
+            ShaderType::Ring(_) | ShaderType::Ship | ShaderType::BlinnPhong(_) => CullMode::None,
+            _ => CullMode::Back,
+        };
+
+        Material {
+            shader,
+            cull_mode,
+            blend_mode: BlendMode::Opaque,
+            depth_write: true,
+            displacement: None,
+        }
+    }
+
+    /// para alturas que cambian con el tiempo; ningún cuerpo de la escena lo usa todavía
+    #[allow(dead_code)]
+    pub fn with_displacement(self, displacement: Rc<Displacement>) -> Self {
+        Material { displacement: Some(displacement), ..self }
+    }
+
+    /// material transparente, por defecto no escribe profundidad para no tapar lo de atrás
+    pub fn blended(shader: ShaderType, blend_mode: BlendMode) -> Self {
+        Material {
+            blend_mode,
+            depth_write: false,
+            ..Material::new(shader)
+        }
+    }
+
+    /// el estado de render sale del propio material de glTF
+    pub fn from_pbr(material: Rc<PbrMaterial>) -> Self {
+        let double_sided = material.double_sided;
+        let mut render_material = match material.alpha_mode {
+            AlphaMode::Blend => Material::blended(ShaderType::Pbr(material), BlendMode::Alpha),
+            // Mask es opaco y escribe profundidad; lo de abajo del corte se descarta antes del depth test
+            AlphaMode::Opaque | AlphaMode::Mask(_) => Material::new(ShaderType::Pbr(material)),
+        };
+        if double_sided {
+            render_material.cull_mode = CullMode::None;
+        }
+        render_material
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode != BlendMode::Opaque
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cull_modes_by_signed_area() {
+        // área positiva: la cara mira a la cámara
+        assert!(!CullMode::Back.culls(2.0));
+        assert!(CullMode::Back.culls(-2.0));
+        assert!(CullMode::Front.culls(2.0));
+        assert!(!CullMode::Front.culls(-2.0));
+        assert!(!CullMode::None.culls(2.0));
+        assert!(!CullMode::None.culls(-2.0));
+        // sin área no hay nada que dibujar en ningún modo
+        for mode in [CullMode::Back, CullMode::Front, CullMode::None] {
+            assert!(mode.culls(0.0));
+        }
+    }
+
+    #[test]
+    fn mask_is_opaque_and_cuts_below_the_cutoff() {
+        use nalgebra_glm::{Vec2, Vec3, Vec4};
+
+        let pbr = |alpha_mode| Rc::new(PbrMaterial {
+            base_color: Vec4::new(1.0, 1.0, 1.0, 0.4),
+            base_color_map: None,
+            metallic: 0.0,
+            roughness: 1.0,
+            metallic_roughness_map: None,
+            emissive: Vec3::zeros(),
+            emissive_map: None,
+            normal_map: None,
+            normal_scale: 1.0,
+            alpha_mode,
+            double_sided: false,
+        });
+
+        // Mask escribe profundidad como lo opaco; Blend no
+        let mask = Material::from_pbr(pbr(AlphaMode::Mask(0.5)));
+        assert!(!mask.is_transparent());
+        assert!(mask.depth_write);
+        let blend = Material::from_pbr(pbr(AlphaMode::Blend));
+        assert!(blend.is_transparent());
+        assert!(!blend.depth_write);
+
+        // alpha 0.4: se recorta con corte 0.5 y pasa con 0.3
+        let uv = Vec2::new(0.5, 0.5);
+        assert!(pbr(AlphaMode::Mask(0.5)).cuts_out(uv));
+        assert!(!pbr(AlphaMode::Mask(0.3)).cuts_out(uv));
+        assert!(!pbr(AlphaMode::Blend).cuts_out(uv));
+    }
+}
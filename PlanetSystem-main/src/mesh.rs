@@ -0,0 +1,266 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::mesh_processing::{self, Normals};
+use crate::vertex::Vertex;
+
+/// vértices sin repetir y cada triángulo como tres índices a ellos
+#[derive(Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    // distancia del vértice más lejano al origen del modelo, para el culling
+    pub radius: f32,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        let radius = vertices.iter().map(|v| v.position.magnitude()).fold(0.0, f32::max);
+        Mesh { vertices, indices, radius }
+    }
+
+    /// la normal de cada cara en sus tres vértices, para que se vea facetado
+    pub fn with_flat_normals(&self) -> Mesh {
+        mesh_processing::generate_normals(self, Normals::Flat)
+    }
+}
+
+// mallas generadas en código
+
+/// esfera por paralelos y meridianos
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let mut vertices = Vec::with_capacity(((segments + 1) * (rings + 1)) as usize);
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let phi = v * PI;
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let theta = u * 2.0 * PI;
+            let normal = Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin());
+            vertices.push(Vertex::new(normal * radius, normal, Vec2::new(u, v)));
+        }
+    }
+
+    let stride = segments + 1;
+    let mut indices = Vec::with_capacity((segments * rings * 6) as usize);
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let i0 = ring * stride + segment;
+            let i1 = i0 + 1;
+            let i2 = i0 + stride;
+            let i3 = i2 + 1;
+            // en los polos un triángulo de cada par queda sin área
+            if ring != 0 {
+                indices.extend_from_slice(&[i0, i1, i2]);
+            }
+            if ring != rings - 1 {
+                indices.extend_from_slice(&[i1, i3, i2]);
+            }
+        }
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+/// icosaedro subdividido, los triángulos salen casi del mismo tamaño
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        Vec3::new(-1.0, t, 0.0), Vec3::new(1.0, t, 0.0), Vec3::new(-1.0, -t, 0.0), Vec3::new(1.0, -t, 0.0),
+        Vec3::new(0.0, -1.0, t), Vec3::new(0.0, 1.0, t), Vec3::new(0.0, -1.0, -t), Vec3::new(0.0, 1.0, -t),
+        Vec3::new(t, 0.0, -1.0), Vec3::new(t, 0.0, 1.0), Vec3::new(-t, 0.0, -1.0), Vec3::new(-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|p| p.normalize())
+    .collect();
+
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // cada arista se parte una sola vez para que los vecinos compartan el punto
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, points: &mut Vec<Vec3>| -> u32 {
+            let key = (a.min(b), a.max(b));
+            *midpoints.entry(key).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalize());
+                (points.len() - 1) as u32
+            })
+        };
+
+        let mut next = Vec::with_capacity(faces.len() * 4);
+        for &[a, b, c] in &faces {
+            let ab = midpoint(a, b, &mut points);
+            let bc = midpoint(b, c, &mut points);
+            let ca = midpoint(c, a, &mut points);
+            next.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        faces = next;
+    }
+
+    let vertices: Vec<Vertex> = points
+        .iter()
+        .map(|&normal| {
+            let u = normal.z.atan2(normal.x) / (2.0 * PI) + 0.5;
+            let v = normal.y.clamp(-1.0, 1.0).acos() / PI;
+            Vertex::new(normal * radius, normal, Vec2::new(u, v))
+        })
+        .collect();
+    let indices: Vec<u32> = faces.into_iter().flatten().collect();
+
+    Mesh::new(vertices, indices)
+}
+
+/// dona acostada en el plano XZ: major es el radio hasta el centro del tubo y minor el del tubo,
+/// así va de major - minor a major + minor
+#[allow(dead_code)]
+pub fn torus(major: f32, minor: f32, segments: u32, rings: u32) -> Mesh {
+    let mut vertices = Vec::with_capacity(((segments + 1) * (rings + 1)) as usize);
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let theta = u * 2.0 * PI;
+        let ring_dir = Vec3::new(theta.cos(), 0.0, theta.sin());
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let phi = v * 2.0 * PI;
+            let normal = ring_dir * phi.cos() + Vec3::new(0.0, phi.sin(), 0.0);
+            let position = ring_dir * major + normal * minor;
+            vertices.push(Vertex::new(position, normal, Vec2::new(u, v)));
+        }
+    }
+
+    let stride = rings + 1;
+    let mut indices = Vec::with_capacity((segments * rings * 6) as usize);
+    for segment in 0..segments {
+        for ring in 0..rings {
+            let i0 = segment * stride + ring;
+            let i1 = i0 + 1;
+            let i2 = i0 + stride;
+            let i3 = i2 + 1;
+            indices.extend_from_slice(&[i0, i1, i2, i1, i3, i2]);
+        }
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+/// anillo plano en XZ mirando hacia +Y; u es el ángulo y v va de adentro (0) hacia afuera (1)
+pub fn annulus(inner_radius: f32, outer_radius: f32, segments: u32) -> Mesh {
+    let normal = Vec3::new(0.0, 1.0, 0.0);
+
+    let mut vertices = Vec::with_capacity(((segments + 1) * 2) as usize);
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let theta = u * 2.0 * PI;
+        let dir = Vec3::new(theta.cos(), 0.0, theta.sin());
+        vertices.push(Vertex::new(dir * inner_radius, normal, Vec2::new(u, 0.0)));
+        vertices.push(Vertex::new(dir * outer_radius, normal, Vec2::new(u, 1.0)));
+    }
+
+    let mut indices = Vec::with_capacity((segments * 6) as usize);
+    for segment in 0..segments {
+        let inner = segment * 2;
+        let outer = inner + 1;
+        let next_inner = inner + 2;
+        let next_outer = inner + 3;
+        indices.extend_from_slice(&[inner, next_inner, outer, outer, next_inner, next_outer]);
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+/// disco en XY hecho en abanico desde el centro, mirando hacia +Z
+pub fn disc(radius: f32, segments: u32) -> Mesh {
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    let mut vertices = Vec::with_capacity((segments + 2) as usize);
+    vertices.push(Vertex::new(Vec3::new(0.0, 0.0, 0.0), normal, Vec2::new(0.5, 0.5)));
+    for segment in 0..=segments {
+        let theta = segment as f32 / segments as f32 * 2.0 * PI;
+        let (sin, cos) = theta.sin_cos();
+        vertices.push(Vertex::new(Vec3::new(cos * radius, sin * radius, 0.0), normal, Vec2::new(0.5 + cos * 0.5, 0.5 - sin * 0.5)));
+    }
+
+    let mut indices = Vec::with_capacity((segments * 3) as usize);
+    for segment in 0..segments {
+        indices.extend_from_slice(&[0, segment + 1, segment + 2]);
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+/// rectángulo en XY centrado en el origen mirando hacia +Z
+pub fn quad(width: f32, height: f32) -> Mesh {
+    let (hw, hh) = (width * 0.5, height * 0.5);
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    let vertices = [
+        Vertex::new(Vec3::new(-hw, -hh, 0.0), normal, Vec2::new(0.0, 1.0)),
+        Vertex::new(Vec3::new(hw, -hh, 0.0), normal, Vec2::new(1.0, 1.0)),
+        Vertex::new(Vec3::new(hw, hh, 0.0), normal, Vec2::new(1.0, 0.0)),
+        Vertex::new(Vec3::new(-hw, hh, 0.0), normal, Vec2::new(0.0, 0.0)),
+    ];
+
+    Mesh::new(vertices.to_vec(), vec![0, 1, 2, 0, 2, 3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // normal de cada triángulo según el orden de sus vértices
+    fn face_normals(mesh: &Mesh) -> Vec<(Vec3, Vec3)> {
+        mesh.indices.chunks_exact(3).map(|tri| {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| mesh.vertices[i as usize].position);
+            ((b - a).cross(&(c - a)), (a + b + c) / 3.0)
+        }).collect()
+    }
+
+    #[test]
+    fn uv_sphere_counts_and_winding() {
+        let sphere = uv_sphere(2.0, 16, 8);
+        assert_eq!(sphere.vertices.len(), 17 * 9);
+        // los polos pierden un triángulo por cuadrito
+        assert_eq!(sphere.indices.len(), 3 * 16 * (2 * 8 - 2));
+        assert!((sphere.radius - 2.0).abs() < 1e-5);
+        for (normal, center) in face_normals(&sphere) {
+            assert!(normal.dot(&center) > 0.0, "triángulo mirando hacia adentro");
+        }
+    }
+
+    #[test]
+    fn icosphere_counts_and_winding() {
+        let sphere = icosphere(1.0, 2);
+        assert_eq!(sphere.vertices.len(), 10 * 16 + 2);
+        assert_eq!(sphere.indices.len(), 3 * 20 * 16);
+        for (normal, center) in face_normals(&sphere) {
+            assert!(normal.dot(&center) > 0.0, "triángulo mirando hacia adentro");
+        }
+    }
+
+    #[test]
+    fn torus_counts_and_winding() {
+        let donut = torus(2.0, 0.5, 24, 12);
+        assert_eq!(donut.vertices.len(), 25 * 13);
+        assert_eq!(donut.indices.len(), 6 * 24 * 12);
+        assert!((donut.radius - 2.5).abs() < 1e-5);
+        // hacia afuera es alejándose del centro del tubo, no del origen
+        for (normal, center) in face_normals(&donut) {
+            let tube = Vec3::new(center.x, 0.0, center.z).normalize() * 2.0;
+            assert!(normal.dot(&(center - tube)) > 0.0, "triángulo mirando hacia adentro");
+        }
+    }
+
+    #[test]
+    fn annulus_counts_and_winding() {
+        let ring = annulus(1.5, 2.9, 32);
+        assert_eq!(ring.vertices.len(), 2 * 33);
+        assert_eq!(ring.indices.len(), 6 * 32);
+        for (normal, _) in face_normals(&ring) {
+            assert!(normal.y > 0.0, "triángulo mirando hacia abajo");
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use nalgebra_glm::{Vec3, Vec4};
+use std::collections::HashMap;
+use std::fmt;
+use crate::mesh::Mesh;
+use crate::vertex::Vertex;
+
+/// caras que forman menos de este ángulo se suavizan juntas
+pub const DEFAULT_SMOOTHING_ANGLE: f32 = 60.0;
+
+/// lo que no se puede arreglar de un mesh que viene de un archivo
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    Empty,
+    // la cantidad de índices no es múltiplo de 3
+    NotTriangles(usize),
+    IndexOutOfRange { index: u32, vertex_count: usize },
+    NonFinitePosition(usize),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::Empty => write!(f, "el mesh no tiene triángulos"),
+            MeshError::NotTriangles(count) => write!(f, "{} índices no forman triángulos", count),
+            MeshError::IndexOutOfRange { index, vertex_count } => {
+                write!(f, "el índice {} se sale de los {} vértices", index, vertex_count)
+            }
+            MeshError::NonFinitePosition(vertex) => write!(f, "el vértice {} no tiene posición finita", vertex),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// cómo se sacan las normales que no vienen en el archivo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normals {
+    // una por cara, se ve facetado
+    Flat,
+    // promedio de las caras vecinas que no se doblan más que el ángulo (en grados)
+    Smooth { max_angle: f32 },
+}
+
+/// qué traía el archivo además de las posiciones
+#[derive(Debug, Clone, Copy)]
+pub struct Attributes {
+    pub normals: bool,
+    pub tangents: bool,
+}
+
+/// valida, quita triángulos sin área y completa normales y tangentes que falten
+pub fn prepare(vertices: Vec<Vertex>, indices: Vec<u32>, attributes: Attributes, normals: Normals) -> Result<Mesh, MeshError> {
+    validate(&vertices, &indices)?;
+    let indices = remove_degenerate(&vertices, &indices);
+    if indices.is_empty() {
+        return Err(MeshError::Empty);
+    }
+
+    let mut mesh = Mesh::new(vertices, indices);
+    if !attributes.normals {
+        mesh = generate_normals(&mesh, normals);
+    }
+    if !attributes.tangents {
+        generate_tangents(&mut mesh);
+    }
+    Ok(mesh)
+}
+
+pub fn validate(vertices: &[Vertex], indices: &[u32]) -> Result<(), MeshError> {
+    if indices.is_empty() {
+        return Err(MeshError::Empty);
+    }
+    if !indices.len().is_multiple_of(3) {
+        return Err(MeshError::NotTriangles(indices.len()));
+    }
+    if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertices.len()) {
+        return Err(MeshError::IndexOutOfRange { index, vertex_count: vertices.len() });
+    }
+    if let Some(vertex) = vertices.iter().position(|vertex| !vertex.position.iter().all(|c| c.is_finite())) {
+        return Err(MeshError::NonFinitePosition(vertex));
+    }
+    Ok(())
+}
+
+/// sin índices repetidos ni área cero; los índices ya tienen que estar validados
+pub fn remove_degenerate(vertices: &[Vertex], indices: &[u32]) -> Vec<u32> {
+    indices
+        .chunks_exact(3)
+        .filter(|tri| {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| vertices[i as usize].position);
+            tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2] && (b - a).cross(&(c - a)).magnitude_squared() > f32::EPSILON * f32::EPSILON
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// normales nuevas para todo el mesh; un vértice se parte cuando sus caras no se suavizan juntas
+pub fn generate_normals(mesh: &Mesh, normals: Normals) -> Mesh {
+    let min_cos = match normals {
+        Normals::Flat => 1.0,
+        Normals::Smooth { max_angle } => max_angle.to_radians().cos(),
+    };
+
+    let faces: Vec<[u32; 3]> = mesh.indices.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]).collect();
+    let face_normals: Vec<Vec3> = faces.iter()
+        .map(|&[a, b, c]| {
+            let [a, b, c] = [a, b, c].map(|i| mesh.vertices[i as usize].position);
+            (b - a).cross(&(c - a)).normalize()
+        })
+        .collect();
+
+    // las caras que tocan cada posición con el ángulo que abren ahí, aunque los vértices estén repetidos por las uv;
+    // pesar por ángulo hace que partir una cara en más triángulos no cambie la normal
+    let mut by_position: HashMap<[u32; 3], Vec<(usize, f32)>> = HashMap::new();
+    for (face, tri) in faces.iter().enumerate() {
+        for corner in 0..3 {
+            let [p, next, prev] = [tri[corner], tri[(corner + 1) % 3], tri[(corner + 2) % 3]].map(|i| mesh.vertices[i as usize].position);
+            let angle = (next - p).normalize().dot(&(prev - p).normalize()).clamp(-1.0, 1.0).acos();
+            by_position.entry(position_key(p)).or_default().push((face, angle));
+        }
+    }
+
+    let mut vertices = Vec::with_capacity(mesh.vertices.len());
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    let mut remap: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    for (face, tri) in faces.iter().enumerate() {
+        let own = face_normals[face];
+        for &index in tri {
+            let vertex = &mesh.vertices[index as usize];
+            let neighbours = &by_position[&position_key(vertex.position)];
+            let sum: Vec3 = neighbours.iter()
+                .filter(|&&(other, _)| other == face || face_normals[other].dot(&own) >= min_cos)
+                .map(|&(other, angle)| face_normals[other] * angle)
+                .sum();
+            let normal = if sum.magnitude_squared() > 0.0 { sum.normalize() } else { own };
+
+            // mismo vértice con la misma normal se comparte
+            let new_index = *remap.entry((index, position_key(normal))).or_insert_with(|| {
+                vertices.push(Vertex { normal, ..vertex.clone() });
+                vertices.len() as u32 - 1
+            });
+            indices.push(new_index);
+        }
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+/// tangentes según cómo cambian las uv en cada cara; sin uv sale cualquier perpendicular a la normal
+pub fn generate_tangents(mesh: &mut Mesh) {
+    let mut tangents = vec![Vec3::zeros(); mesh.vertices.len()];
+    let mut bitangents = vec![Vec3::zeros(); mesh.vertices.len()];
+
+    for tri in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| &mesh.vertices[i as usize]);
+        let edge1 = b.position - a.position;
+        let edge2 = c.position - a.position;
+        let duv1 = b.tex_coords - a.tex_coords;
+        let duv2 = c.tex_coords - a.tex_coords;
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() < 1e-12 {
+            continue;
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+        for &index in tri {
+            tangents[index as usize] += tangent;
+            bitangents[index as usize] += bitangent;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in mesh.vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = vertex.normal;
+        // Gram-Schmidt para que quede perpendicular a la normal
+        let mut t = tangent - normal * normal.dot(&tangent);
+        if t.magnitude_squared() < 1e-12 {
+            let axis = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+            t = axis - normal * normal.dot(&axis);
+        }
+        let t = t.normalize();
+        let handedness = if normal.cross(&t).dot(&bitangent) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = Vec4::new(t.x, t.y, t.z, handedness);
+    }
+}
+
+// llave exacta para juntar vértices en la misma posición
+fn position_key(v: Vec3) -> [u32; 3] {
+    [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    fn vertex(x: f32, y: f32, z: f32) -> Vertex {
+        Vertex::new(Vec3::new(x, y, z), Vec3::zeros(), Vec2::new(x, y))
+    }
+
+    // cubo de 8 esquinas compartidas, sin normales
+    fn cube() -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = (0..8).map(|i| vertex((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32)).collect();
+        let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let indices = quads.iter().flat_map(|&[a, b, c, d]| [a, b, c, a, c, d]).collect();
+        (vertices, indices)
+    }
+
+    const NOTHING: Attributes = Attributes { normals: false, tangents: false };
+
+    #[test]
+    fn rejects_indices_out_of_range() {
+        let vertices = vec![vertex(0.0, 0.0, 0.0), vertex(1.0, 0.0, 0.0), vertex(0.0, 1.0, 0.0)];
+        let result = prepare(vertices, vec![0, 1, 3], NOTHING, Normals::Flat);
+        assert!(matches!(result, Err(MeshError::IndexOutOfRange { index: 3, vertex_count: 3 })));
+    }
+
+    #[test]
+    fn drops_degenerate_triangles() {
+        let vertices = vec![vertex(0.0, 0.0, 0.0), vertex(1.0, 0.0, 0.0), vertex(0.0, 1.0, 0.0), vertex(2.0, 0.0, 0.0)];
+        // uno bueno, uno con índice repetido y uno con los tres puntos en línea
+        let mesh = prepare(vertices, vec![0, 1, 2, 0, 0, 1, 0, 1, 3], NOTHING, Normals::Flat).unwrap();
+        assert_eq!(mesh.indices.len(), 3);
+
+        let line = vec![vertex(0.0, 0.0, 0.0), vertex(1.0, 0.0, 0.0), vertex(2.0, 0.0, 0.0)];
+        assert_eq!(prepare(line, vec![0, 1, 2], NOTHING, Normals::Flat).err(), Some(MeshError::Empty));
+    }
+
+    #[test]
+    fn smoothing_angle_splits_hard_edges() {
+        let (vertices, indices) = cube();
+        // a 90° entre caras, con 60° cada esquina se parte en sus tres caras
+        let sharp = prepare(vertices.clone(), indices.clone(), NOTHING, Normals::Smooth { max_angle: 60.0 }).unwrap();
+        assert_eq!(sharp.vertices.len(), 24);
+        for vertex in &sharp.vertices {
+            assert_eq!(vertex.normal.abs().sum(), 1.0);
+        }
+
+        // con 100° todo se suaviza y quedan las 8 esquinas apuntando hacia afuera
+        let smooth = prepare(vertices, indices, NOTHING, Normals::Smooth { max_angle: 100.0 }).unwrap();
+        assert_eq!(smooth.vertices.len(), 8);
+        for vertex in &smooth.vertices {
+            let outward = (vertex.position - Vec3::repeat(0.5)).normalize();
+            assert!(vertex.normal.dot(&outward) > 0.99);
+        }
+    }
+
+    #[test]
+    fn tangents_follow_u_and_stay_perpendicular() {
+        let (vertices, indices) = cube();
+        let mesh = prepare(vertices, indices, NOTHING, Normals::Flat).unwrap();
+        for vertex in &mesh.vertices {
+            let tangent = vertex.tangent.xyz();
+            assert!((tangent.magnitude() - 1.0).abs() < 1e-5);
+            assert!(tangent.dot(&vertex.normal).abs() < 1e-5);
+            assert!(vertex.tangent.w == 1.0 || vertex.tangent.w == -1.0);
+
+            // en las caras de frente y de atrás la uv es (x, y): la tangente va hacia +x
+            // y la bitangente hacia +y, así que el signo cambia con la normal
+            if vertex.normal.z.abs() == 1.0 {
+                assert!((tangent - Vec3::x()).magnitude() < 1e-5);
+                assert_eq!(vertex.tangent.w, vertex.normal.z);
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use nalgebra_glm::Vec3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use crate::cubemap::Cubemap;
+use crate::noise;
+use crate::rings::smoothstep;
+use crate::skybox::celestial_direction;
+
+// polo norte y centro de la galaxia en ascensión recta (horas) y declinación (grados)
+const GALACTIC_POLE: (f32, f32) = (12.857, 27.13);
+const GALACTIC_CENTER: (f32, f32) = (17.761, -28.94);
+const NEBULA_COUNT: usize = 5;
+// colores típicos: hidrógeno, oxígeno, reflexión azul y polvo caliente
+const NEBULA_COLORS: [[f32; 3]; 4] = [[0.85, 0.25, 0.55], [0.2, 0.65, 0.7], [0.3, 0.4, 0.95], [0.9, 0.4, 0.2]];
+
+/// nube de colores alrededor de una dirección del cielo
+struct Nebula {
+    center: Vec3,
+    // ancho en radianes
+    radius: f32,
+    color: Vec3,
+    seed: u32,
+}
+
+/// cielo procedural: la banda de la vía láctea con su polvo y nubes de colores, horneado en un cubemap
+pub fn bake(seed: u64, face_size: usize) -> Cubemap {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let noise_seed: u32 = rng.gen();
+    let nebulae: Vec<Nebula> = (0..NEBULA_COUNT)
+        .map(|_| {
+            // dirección pareja sobre la esfera
+            let y: f32 = rng.gen_range(-1.0..=1.0);
+            let theta = rng.gen::<f32>() * 2.0 * std::f32::consts::PI;
+            let ring = (1.0 - y * y).sqrt();
+            Nebula {
+                center: Vec3::new(ring * theta.cos(), y, ring * theta.sin()),
+                radius: rng.gen_range(0.15..0.45),
+                color: Vec3::from(NEBULA_COLORS[rng.gen_range(0..NEBULA_COLORS.len())]),
+                seed: rng.gen(),
+            }
+        })
+        .collect();
+
+    let pole = celestial_direction(GALACTIC_POLE.0, GALACTIC_POLE.1);
+    let center = celestial_direction(GALACTIC_CENTER.0, GALACTIC_CENTER.1);
+    Cubemap::from_fn(face_size, |direction| {
+        let milky_way = milky_way(direction, pole, center, noise_seed);
+        let clouds: Vec3 = nebulae.iter().map(|nebula| nebula_color(direction, nebula)).sum();
+        (milky_way + clouds).map(|c| c.clamp(0.0, 1.0))
+    })
+}
+
+// banda angosta sobre el plano de la galaxia, más ancha y cálida hacia el centro, cortada por polvo
+fn milky_way(direction: Vec3, pole: Vec3, center: Vec3, seed: u32) -> Vec3 {
+    let toward_center = direction.dot(&center) * 0.5 + 0.5;
+    // los bordes ondulan para que no sea una franja recta
+    let latitude = direction.dot(&pole) + noise::fbm(direction * 3.0, 3, seed) * 0.08;
+    let width = 0.12 + 0.1 * toward_center.powi(4);
+    let band = (-(latitude / width).powi(2)).exp();
+    if band < 0.01 {
+        return Vec3::zeros();
+    }
+
+    let clumps = noise::fbm(direction * 9.0, 5, seed.wrapping_add(10)) * 0.5 + 0.5;
+    let glow = band * (0.35 + 0.65 * clumps) * (0.35 + 0.65 * toward_center.powi(3));
+
+    // franja de polvo oscuro por el medio, con huecos
+    let lane = (-(latitude / (width * 0.3)).powi(2)).exp();
+    let dust = smoothstep(-0.1, 0.25, noise::fbm(direction * 6.0, 4, seed.wrapping_add(20)));
+    let glow = glow * (1.0 - 0.85 * lane * dust);
+
+    let color = Vec3::new(0.55, 0.6, 0.75).lerp(&Vec3::new(0.95, 0.85, 0.7), toward_center);
+    color * glow * 0.3
+}
+
+// nube que se desvanece con la distancia angular al centro, deshilachada con ruido
+fn nebula_color(direction: Vec3, nebula: &Nebula) -> Vec3 {
+    let angle = direction.dot(&nebula.center).clamp(-1.0, 1.0).acos();
+    let falloff = (-(angle / nebula.radius).powi(2)).exp();
+    if falloff < 0.01 {
+        return Vec3::zeros();
+    }
+    let wisps = (noise::fbm(direction * 5.0, 5, nebula.seed) * 0.5 + 0.5).powi(3) * 2.0;
+    nebula.color * (falloff * wisps * 0.45)
+}
@@ -0,0 +1,42 @@
+use nalgebra_glm::Vec3;
+
+/// octavas de value_noise, cada una al doble de frecuencia y la mitad de peso; más o menos de -1 a 1
+pub fn fbm(p: Vec3, octaves: u32, seed: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut frequency = 1.0;
+    let mut weight = 0.5;
+    for octave in 0..octaves {
+        sum += value_noise(p * frequency, seed.wrapping_add(octave)) * weight;
+        frequency *= 2.0;
+        weight *= 0.5;
+    }
+    sum
+}
+
+/// ruido suave entre los valores al azar de las esquinas de la celda, de -1 a 1
+pub fn value_noise(p: Vec3, seed: u32) -> f32 {
+    let cell = p.map(f32::floor);
+    let f = p - cell;
+    let s = f.map(|t| t * t * (3.0 - 2.0 * t));
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        hash(cell.x as i32 + dx, cell.y as i32 + dy, cell.z as i32 + dz, seed)
+    };
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), s.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), s.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), s.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), s.x);
+    lerp(lerp(x00, x10, s.y), lerp(x01, x11, s.y), s.z)
+}
+
+fn hash(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f)
+        ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h & 0xffff) as f32 / 32767.5 - 1.0
+}
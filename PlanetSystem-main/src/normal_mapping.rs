@@ -0,0 +1,122 @@
+use nalgebra_glm::{mat4_to_mat3, Mat3, Mat4, Vec2, Vec3};
+use crate::fragments::{Fragments, LIGHT_DIR};
+use crate::texture::Texture;
+
+/// tangente, bitangente y normal del fragmento en mundo, perpendiculares entre sí
+pub struct TangentFrame {
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub normal: Vec3,
+}
+
+impl TangentFrame {
+    pub fn new(fragment: &Fragments) -> Self {
+        let normal = fragment.normal;
+        // la tangente interpolada ya no es perpendicular; sin tangente sirve cualquier dirección del plano
+        let mut tangent = fragment.tangent.xyz() - normal * normal.dot(&fragment.tangent.xyz());
+        if tangent.magnitude_squared() < 1e-12 {
+            let axis = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+            tangent = axis - normal * normal.dot(&axis);
+        }
+        let tangent = tangent.normalize();
+        let handedness = if fragment.tangent.w < 0.0 { -1.0 } else { 1.0 };
+
+        TangentFrame { tangent, bitangent: normal.cross(&tangent) * handedness, normal }
+    }
+
+    /// de espacio tangente (x sobre u, y sobre v, z hacia afuera) a mundo
+    pub fn to_world(&self, v: Vec3) -> Vec3 {
+        self.tangent * v.x + self.bitangent * v.y + self.normal * v.z
+    }
+}
+
+/// normal de un mapa de normales en espacio tangente; strength escala la inclinación
+pub fn normal_from_map(fragment: &Fragments, texture: &Texture, uv: Vec2, strength: f32) -> Vec3 {
+    let texel = texture.sample(uv) * 2.0 - Vec3::repeat(1.0);
+    let tangent_normal = Vec3::new(texel.x * strength, texel.y * strength, texel.z.max(1e-3));
+    TangentFrame::new(fragment).to_world(tangent_normal).normalize()
+}
+
+/// normal inclinada según una altura definida sobre la posición en el modelo;
+/// la pendiente se saca por diferencias y solo cuenta la parte que va sobre la superficie
+pub fn bump_normal(fragment: &Fragments, model_matrix: &Mat4, height: impl Fn(Vec3) -> f32, strength: f32) -> Vec3 {
+    const STEP: f32 = 1e-3;
+    let p = fragment.vertex_pos;
+    let gradient = Vec3::new(
+        height(p + Vec3::x() * STEP) - height(p - Vec3::x() * STEP),
+        height(p + Vec3::y() * STEP) - height(p - Vec3::y() * STEP),
+        height(p + Vec3::z() * STEP) - height(p - Vec3::z() * STEP),
+    ) / (2.0 * STEP);
+
+    // el gradiente se transforma como las normales; la altura crece con la escala del modelo, la pendiente no
+    let model: Mat3 = mat4_to_mat3(model_matrix);
+    let normal_matrix = model.try_inverse().map_or(Mat3::identity(), |m| m.transpose());
+    let gradient = normal_matrix * gradient * model.determinant().abs().cbrt();
+    let normal = fragment.normal;
+    let surface_gradient = gradient - normal * normal.dot(&gradient);
+
+    (normal - surface_gradient * strength).normalize()
+}
+
+/// lo mismo que intensity pero con otra normal
+pub fn intensity(normal: Vec3) -> f32 {
+    normal.dot(&LIGHT_DIR).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec4;
+
+    // fragmento de una cara inclinada, con la tangente un poco torcida como queda al interpolar
+    fn fragment() -> Fragments {
+        Fragments {
+            position: Vec2::zeros(),
+            depth: 0.0,
+            normal: Vec3::new(0.0, 1.0, 1.0).normalize(),
+            tangent: Vec4::new(1.0, 0.2, 0.0, -1.0),
+            intensity: 0.0,
+            vertex_pos: Vec3::new(0.3, 0.4, 0.5),
+            tex_coords: Vec2::zeros(),
+            coverage: 1,
+            depth_slope: Vec2::zeros(),
+        }
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).magnitude() < 1e-5
+    }
+
+    #[test]
+    fn tangent_frame_is_orthonormal() {
+        let frame = TangentFrame::new(&fragment());
+        for v in [frame.tangent, frame.bitangent, frame.normal] {
+            assert!((v.magnitude() - 1.0).abs() < 1e-5);
+        }
+        assert!(frame.tangent.dot(&frame.normal).abs() < 1e-5);
+        assert!(frame.bitangent.dot(&frame.normal).abs() < 1e-5);
+        assert!(frame.tangent.dot(&frame.bitangent).abs() < 1e-5);
+        // w negativo da vuelta la bitangente
+        assert!(close(frame.bitangent, -frame.normal.cross(&frame.tangent)));
+    }
+
+    #[test]
+    fn flat_texel_keeps_the_geometric_normal() {
+        let fragment = fragment();
+        let texture = Texture::new(1, 1, vec![Vec3::new(0.5, 0.5, 1.0)]);
+        let normal = normal_from_map(&fragment, &texture, Vec2::new(0.5, 0.5), 1.0);
+        assert!(close(normal, fragment.normal));
+    }
+
+    #[test]
+    fn flat_height_keeps_the_geometric_normal() {
+        let fragment = fragment();
+        let model = nalgebra_glm::scaling(&Vec3::repeat(2.0));
+        let normal = bump_normal(&fragment, &model, |_| 0.25, 3.0);
+        assert!(close(normal, fragment.normal));
+
+        // una rampa en x sí la inclina, hacia -x
+        let tilted = bump_normal(&fragment, &model, |p| p.x, 1.0);
+        assert!(tilted.x < -0.1);
+    }
+}
@@ -1,62 +1,166 @@
-use tobj;
 use nalgebra_glm::{Vec2, Vec3};
+use std::path::Path;
+use std::rc::Rc;
+use crate::load_error::LoadError;
+use crate::mesh::Mesh;
+use crate::mesh_processing::{self, Attributes, Normals, DEFAULT_SMOOTHING_ANGLE};
+use crate::phong::BlinnPhong;
 use crate::vertex::Vertex;
 
 pub struct Obj {
-    meshes: Vec<Mesh>,
+    meshes: Vec<ObjMesh>,
+    materials: Vec<Rc<BlinnPhong>>,
 }
 
-struct Mesh {
-    vertices: Vec<Vec3>,
-    normals: Vec<Vec3>,
-    texcoords: Vec<Vec2>,
-    indices: Vec<u32>,
+/// la parte del modelo que usa un mismo material (None si el .obj no trae)
+pub struct SubMesh {
+    pub mesh: Mesh,
+    pub material: Option<Rc<BlinnPhong>>,
+}
+
+// un objeto del archivo ya validado y con normales y tangentes
+struct ObjMesh {
+    mesh: Mesh,
+    material_id: Option<usize>,
 }
 
 impl Obj {
-    pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
-            single_index: true,
-            triangulate: true,
-            ..Default::default()
-        })?;
-
-        let meshes = models.into_iter().map(|model| {
+    pub fn load(filename: &str) -> Result<Self, LoadError> {
+        let (models, materials) = tobj::load_obj(filename, &load_options())
+            .map_err(|error| LoadError::Obj { path: filename.to_string(), error })?;
+        Obj::from_models(filename, models, materials)
+    }
+
+    // lo que devuelve tobj ya parseado; filename es para los mensajes y para buscar las texturas
+    fn from_models(filename: &str, models: Vec<tobj::Model>, materials: Result<Vec<tobj::Material>, tobj::LoadError>) -> Result<Self, LoadError> {
+        // si el .mtl no está se sigue sin materiales, la nave tiene su shader de respaldo
+        let directory = Path::new(filename).parent().unwrap_or(Path::new(""));
+        let materials = materials.unwrap_or_else(|error| {
+            eprintln!("no se pudieron cargar los materiales de {}: {}", filename, error);
+            Vec::new()
+        });
+        let materials: Vec<_> = materials.iter()
+            .map(|material| Rc::new(BlinnPhong::from_mtl(material, directory)))
+            .collect();
+
+        // objetos sin caras (solo puntos o líneas) no se dibujan
+        let meshes = models.into_iter().filter(|model| !model.mesh.indices.is_empty()).map(|model| {
             let mesh = model.mesh;
-            Mesh {
-                vertices: mesh.positions.chunks(3)
-                    .map(|v| Vec3::new(v[0], v[1], v[2]))
-                    .collect(),
-                normals: mesh.normals.chunks(3)
-                    .map(|n| Vec3::new(n[0], n[1], n[2]))
-                    .collect(),
-                texcoords: mesh.texcoords.chunks(2)
-                    .map(|t| Vec2::new(t[0], 1.0 - t[1]))
-                    .collect(),
-                indices: mesh.indices,
-            }
-        }).collect();
+            // con single_index las normales y uv vienen completas o no vienen
+            let has_normals = mesh.normals.len() == mesh.positions.len();
+            let vertices = mesh.positions.chunks(3).enumerate()
+                .map(|(i, v)| {
+                    let normal = mesh.normals.get(i * 3..i * 3 + 3)
+                        .map_or(Vec3::new(0.0, 1.0, 0.0), |n| Vec3::new(n[0], n[1], n[2]));
+                    let tex_coords = mesh.texcoords.get(i * 2..i * 2 + 2)
+                        .map_or(Vec2::new(0.0, 0.0), |t| Vec2::new(t[0], 1.0 - t[1]));
+                    Vertex::new(Vec3::new(v[0], v[1], v[2]), normal, tex_coords)
+                })
+                .collect();
 
-        Ok(Obj { meshes })
-    }
+            let attributes = Attributes { normals: has_normals, tangents: false };
+            let normals = Normals::Smooth { max_angle: DEFAULT_SMOOTHING_ANGLE };
+            let prepared = mesh_processing::prepare(vertices, mesh.indices, attributes, normals)
+                .map_err(|error| LoadError::Mesh { path: format!("{} ({})", filename, model.name), error })?;
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
+            Ok(ObjMesh {
+                mesh: prepared,
+                // un id que no está en el .mtl cuenta como sin material
+                material_id: mesh.material_id.filter(|&id| id < materials.len()),
+            })
+        }).collect::<Result<_, LoadError>>()?;
 
+        Ok(Obj { meshes, materials })
+    }
+
+    /// un mesh por material, juntando los objetos que comparten el mismo
+    pub fn get_submeshes(&self) -> Vec<SubMesh> {
+        let mut material_ids: Vec<Option<usize>> = Vec::new();
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
-
-                vertices.push(Vertex::new(position, normal, tex_coords));
+            if !material_ids.contains(&mesh.material_id) {
+                material_ids.push(mesh.material_id);
             }
         }
 
-        vertices
+        material_ids.into_iter()
+            .map(|id| SubMesh {
+                mesh: merge(self.meshes.iter().filter(|mesh| mesh.material_id == id)),
+                material: id.map(|id| self.materials[id].clone()),
+            })
+            .collect()
+    }
+}
+
+fn load_options() -> tobj::LoadOptions {
+    tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ..Default::default()
+    }
+}
+
+// los objetos en un solo mesh indexado
+fn merge<'a>(meshes: impl Iterator<Item = &'a ObjMesh>) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for ObjMesh { mesh, .. } in meshes {
+        // los índices de cada objeto empiezan en 0, se corren después de los anteriores
+        let offset = vertices.len() as u32;
+        vertices.extend(mesh.vertices.iter().cloned());
+        indices.extend(mesh.indices.iter().map(|&index| index + offset));
+    }
+
+    Mesh::new(vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // tres objetos: dos con el mismo material y uno sin material
+    const OBJ: &str = "\
+mtllib test.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 1
+o casco
+usemtl metal
+f 1 2 3
+o ala
+usemtl metal
+f 1 3 4
+o vidrio
+usemtl ninguno
+f 1 2 4
+";
+    const MTL: &str = "\
+newmtl metal
+Kd 0.5 0.5 0.5
+";
+
+    fn load(obj: &str) -> Obj {
+        let (models, materials) = tobj::load_obj_buf(&mut obj.as_bytes(), &load_options(), |_| {
+            tobj::load_mtl_buf(&mut MTL.as_bytes())
+        }).unwrap();
+        Obj::from_models("test.obj", models, materials).unwrap()
+    }
+
+    #[test]
+    fn submeshes_group_objects_by_material() {
+        let submeshes = load(OBJ).get_submeshes();
+        assert_eq!(submeshes.len(), 2);
+
+        // casco y ala juntos, con los índices del segundo corridos después del primero
+        let metal = &submeshes[0];
+        assert_eq!(metal.material.as_ref().unwrap().diffuse, Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(metal.mesh.indices.len(), 6);
+        assert_eq!(metal.mesh.vertices.len(), 6);
+        assert!(metal.mesh.indices[3..].iter().all(|&index| index >= 3));
+
+        // un material que no está en el .mtl queda sin material
+        assert!(submeshes[1].material.is_none());
+        assert_eq!(submeshes[1].mesh.indices.len(), 3);
     }
 }
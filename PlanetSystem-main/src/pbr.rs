@@ -0,0 +1,67 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use std::rc::Rc;
+use crate::texture::Texture;
+
+/// cómo usa el alpha un material de glTF
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    // se descarta lo que quede abajo del corte
+    Mask(f32),
+    Blend,
+}
+
+/// material metallic-roughness de glTF
+pub struct PbrMaterial {
+    pub base_color: Vec4,
+    pub base_color_map: Option<Rc<Texture>>,
+    pub metallic: f32,
+    pub roughness: f32,
+    // roughness en el verde y metallic en el azul, como pide glTF
+    pub metallic_roughness_map: Option<Rc<Texture>>,
+    pub emissive: Vec3,
+    pub emissive_map: Option<Rc<Texture>>,
+    // en espacio tangente; normal_scale escala la inclinación
+    pub normal_map: Option<Rc<Texture>>,
+    pub normal_scale: f32,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+impl PbrMaterial {
+    /// color base con su alpha, multiplicado por la textura si hay
+    pub fn base_color_at(&self, uv: Vec2) -> Vec4 {
+        match &self.base_color_map {
+            Some(texture) => {
+                // el alpha de la textura también cuenta para Mask y Blend
+                self.base_color.component_mul(&texture.sample_rgba(uv))
+            }
+            None => self.base_color,
+        }
+    }
+
+    /// alpha test de Mask: true si el texel queda abajo del corte
+    pub fn cuts_out(&self, uv: Vec2) -> bool {
+        match self.alpha_mode {
+            AlphaMode::Mask(cutoff) => self.base_color_at(uv).w < cutoff,
+            _ => false,
+        }
+    }
+
+    pub fn metallic_roughness_at(&self, uv: Vec2) -> (f32, f32) {
+        match &self.metallic_roughness_map {
+            Some(texture) => {
+                let texel = texture.sample(uv);
+                (self.metallic * texel.z, self.roughness * texel.y)
+            }
+            None => (self.metallic, self.roughness),
+        }
+    }
+
+    pub fn emissive_at(&self, uv: Vec2) -> Vec3 {
+        match &self.emissive_map {
+            Some(texture) => self.emissive.component_mul(&texture.sample(uv)),
+            None => self.emissive,
+        }
+    }
+}
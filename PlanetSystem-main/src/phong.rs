@@ -0,0 +1,106 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::path::Path;
+use std::rc::Rc;
+use crate::texture::Texture;
+
+/// material Blinn-Phong armado con lo que trae un .mtl
+pub struct BlinnPhong {
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub emissive: Vec3,
+    // d del .mtl, 1 es opaco
+    pub opacity: f32,
+    pub diffuse_map: Option<Rc<Texture>>,
+    // map_Bump; Blender exporta ahí el mapa de normales, así que se lee como tal
+    pub bump_map: Option<Rc<Texture>>,
+}
+
+impl BlinnPhong {
+    /// las texturas se buscan relativas a la carpeta del .obj
+    pub fn from_mtl(material: &tobj::Material, directory: &Path) -> Self {
+        let color = |value: Option<[f32; 3]>, default: f32| {
+            value.map_or(Vec3::new(default, default, default), |[r, g, b]| Vec3::new(r, g, b))
+        };
+        // Ke no lo parsea tobj, queda en los parámetros desconocidos
+        let emissive = material.unknown_param.get("Ke")
+            .map(|value| value.split_whitespace().filter_map(|v| v.parse().ok()).collect::<Vec<f32>>())
+            .filter(|values| values.len() == 3)
+            .map_or(Vec3::zeros(), |values| Vec3::new(values[0], values[1], values[2]));
+
+        BlinnPhong {
+            ambient: color(material.ambient, 0.0),
+            diffuse: color(material.diffuse, 0.8),
+            specular: color(material.specular, 0.0),
+            shininess: material.shininess.unwrap_or(1.0).max(1.0),
+            emissive,
+            opacity: material.dissolve.unwrap_or(1.0).clamp(0.0, 1.0),
+            diffuse_map: material.diffuse_texture.as_deref().and_then(|file| load_map(directory, file)),
+            bump_map: material.normal_texture.as_deref().and_then(|file| load_map(directory, file)),
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.opacity < 1.0
+    }
+
+    /// Kd por la textura difusa si hay
+    pub fn diffuse_at(&self, uv: Vec2) -> Vec3 {
+        match &self.diffuse_map {
+            Some(texture) => self.diffuse.component_mul(&texture.sample(uv)),
+            None => self.diffuse,
+        }
+    }
+}
+
+// los mapas pueden traer opciones antes del nombre (-bm 1.0 archivo.png), el archivo va al final
+fn load_map(directory: &Path, value: &str) -> Option<Rc<Texture>> {
+    let file = value.split_whitespace().last()?;
+    let path = directory.join(file);
+    match Texture::load(&path.to_string_lossy()) {
+        Ok(texture) => Some(Rc::new(texture)),
+        Err(error) => {
+            eprintln!("no se pudo cargar la textura {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_mtl(mtl: &str) -> BlinnPhong {
+        let (materials, _) = tobj::load_mtl_buf(&mut mtl.as_bytes()).unwrap();
+        BlinnPhong::from_mtl(&materials[0], Path::new(""))
+    }
+
+    #[test]
+    fn reads_colors_shininess_emission_and_opacity() {
+        let material = from_mtl("\
+newmtl vidrio
+Kd 0.1 0.2 0.3
+Ks 0.5 0.5 0.5
+Ns 250
+Ke 1.0 0.5 0.0
+d 0.4
+");
+        assert_eq!(material.diffuse, Vec3::new(0.1, 0.2, 0.3));
+        assert_eq!(material.specular, Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(material.shininess, 250.0);
+        assert_eq!(material.emissive, Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(material.opacity, 0.4);
+        assert!(material.is_transparent());
+    }
+
+    #[test]
+    fn missing_values_fall_back_to_defaults() {
+        let material = from_mtl("newmtl vacio\nNs 0\n");
+        assert_eq!(material.diffuse, Vec3::new(0.8, 0.8, 0.8));
+        assert_eq!(material.emissive, Vec3::zeros());
+        // Ns 0 rompería el pow del especular
+        assert_eq!(material.shininess, 1.0);
+        assert!(!material.is_transparent());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::color::Color;
+
+// post-procesos sobre la imagen ya resuelta (un u32 por pixel)
+
+/// promedia bloques de factor×factor, para el SSAA
+pub fn downsample(buffer: &[u32], width: usize, height: usize, factor: usize) -> Vec<u32> {
+    let (out_width, out_height) = (width / factor, height / factor);
+    let count = (factor * factor) as u32;
+    let mut out = vec![0; out_width * out_height];
+
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let (mut r, mut g, mut b) = (0, 0, 0);
+            for sy in 0..factor {
+                let row = (y * factor + sy) * width + x * factor;
+                for &pixel in &buffer[row..row + factor] {
+                    r += (pixel >> 16) & 0xFF;
+                    g += (pixel >> 8) & 0xFF;
+                    b += pixel & 0xFF;
+                }
+            }
+            out[y * out_width + x] = (r / count) << 16 | (g / count) << 8 | (b / count);
+        }
+    }
+
+    out
+}
+
+const FXAA_SPAN_MAX: f32 = 8.0;
+const FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+const FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+// contraste mínimo para considerar que hay un borde
+const FXAA_EDGE_THRESHOLD: f32 = 1.0 / 8.0;
+const FXAA_EDGE_THRESHOLD_MIN: f32 = 1.0 / 32.0;
+
+/// FXAA: busca bordes por luminancia y promedia a lo largo de ellos
+pub fn fxaa(buffer: &[u32], width: usize, height: usize) -> Vec<u32> {
+    let image = Image { buffer, width, height };
+    let mut out = buffer.to_vec();
+
+    for y in 0..height {
+        for x in 0..width {
+            let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+            let luma_m = luma(image.texel(x as i32, y as i32));
+            let luma_nw = luma(image.texel(x as i32 - 1, y as i32 - 1));
+            let luma_ne = luma(image.texel(x as i32 + 1, y as i32 - 1));
+            let luma_sw = luma(image.texel(x as i32 - 1, y as i32 + 1));
+            let luma_se = luma(image.texel(x as i32 + 1, y as i32 + 1));
+
+            let luma_min = luma_m.min(luma_nw).min(luma_ne).min(luma_sw).min(luma_se);
+            let luma_max = luma_m.max(luma_nw).max(luma_ne).max(luma_sw).max(luma_se);
+            if luma_max - luma_min < FXAA_EDGE_THRESHOLD_MIN.max(luma_max * FXAA_EDGE_THRESHOLD) {
+                continue;
+            }
+
+            // dirección perpendicular al gradiente, o sea a lo largo del borde
+            let mut dir_x = -((luma_nw + luma_ne) - (luma_sw + luma_se));
+            let mut dir_y = (luma_nw + luma_sw) - (luma_ne + luma_se);
+            let dir_reduce = ((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL).max(FXAA_REDUCE_MIN);
+            let rcp_dir_min = 1.0 / (dir_x.abs().min(dir_y.abs()) + dir_reduce);
+            dir_x = (dir_x * rcp_dir_min).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+            dir_y = (dir_y * rcp_dir_min).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+
+            let sample = |t: f32| image.bilinear(fx + dir_x * t, fy + dir_y * t);
+            let rgb_a = (sample(1.0 / 3.0 - 0.5) + sample(2.0 / 3.0 - 0.5)) * 0.5;
+            let rgb_b = rgb_a * 0.5 + (sample(-0.5) + sample(0.5)) * 0.25;
+
+            let luma_b = luma(rgb_b);
+            let result = if luma_b < luma_min || luma_b > luma_max { rgb_a } else { rgb_b };
+            out[y * width + x] = result.to_hex();
+        }
+    }
+
+    out
+}
+
+struct Image<'a> {
+    buffer: &'a [u32],
+    width: usize,
+    height: usize,
+}
+
+impl Image<'_> {
+    // pixel con el borde repetido
+    fn texel(&self, x: i32, y: i32) -> Rgb {
+        let x = x.clamp(0, self.width as i32 - 1) as usize;
+        let y = y.clamp(0, self.height as i32 - 1) as usize;
+        Rgb::from_hex(self.buffer[y * self.width + x])
+    }
+
+    fn bilinear(&self, x: f32, y: f32) -> Rgb {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.texel(x0, y0) * (1.0 - tx) + self.texel(x0 + 1, y0) * tx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+// color en flotante para poder promediar sin perder precisión
+#[derive(Clone, Copy)]
+struct Rgb {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+impl Rgb {
+    fn from_hex(hex: u32) -> Self {
+        let color = Color::from_hex(hex);
+        Rgb {
+            r: color.r as f32 / 255.0,
+            g: color.g as f32 / 255.0,
+            b: color.b as f32 / 255.0,
+        }
+    }
+
+    fn to_hex(self) -> u32 {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::new(channel(self.r), channel(self.g), channel(self.b)).to_hex()
+    }
+}
+
+impl std::ops::Add for Rgb {
+    type Output = Rgb;
+
+    fn add(self, other: Rgb) -> Rgb {
+        Rgb { r: self.r + other.r, g: self.g + other.g, b: self.b + other.b }
+    }
+}
+
+impl std::ops::Mul<f32> for Rgb {
+    type Output = Rgb;
+
+    fn mul(self, scalar: f32) -> Rgb {
+        Rgb { r: self.r * scalar, g: self.g * scalar, b: self.b * scalar }
+    }
+}
+
+fn luma(rgb: Rgb) -> f32 {
+    rgb.r * 0.299 + rgb.g * 0.587 + rgb.b * 0.114
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_averages_each_block() {
+        // un bloque 2×2 de rojo, verde, azul y blanco
+        let buffer = [0xFF0000, 0x00FF00, 0x0000FF, 0xFFFFFF];
+        assert_eq!(downsample(&buffer, 2, 2, 2), vec![0x7F7F7F]);
+
+        // 4×2 da dos pixeles, cada uno de su propio bloque
+        let buffer = [0x000000, 0x000000, 0x400000, 0x400000, 0x000000, 0x000000, 0x400000, 0x400000];
+        assert_eq!(downsample(&buffer, 4, 2, 2), vec![0x000000, 0x400000]);
+    }
+
+    #[test]
+    fn fxaa_leaves_flat_images_alone() {
+        let buffer = vec![0x336699; 16 * 12];
+        assert_eq!(fxaa(&buffer, 16, 12), buffer);
+    }
+}
@@ -0,0 +1,100 @@
+use std::rc::Rc;
+
+/// densidad del anillo según la distancia al planeta, guardada como textura 1D
+pub struct RingProfile {
+    samples: Vec<f32>,
+}
+
+impl RingProfile {
+    /// bandas tipo Saturno: anillo C tenue, B denso, división de Cassini, A con el hueco de Encke
+    pub fn procedural(resolution: usize) -> Self {
+        let samples = (0..resolution)
+            .map(|i| {
+                let t = i as f32 / (resolution - 1) as f32;
+                let band = |start: f32, end: f32, density: f32| {
+                    density * smoothstep(start - 0.01, start + 0.01, t) * (1.0 - smoothstep(end - 0.01, end + 0.01, t))
+                };
+
+                let c_ring = band(0.0, 0.25, 0.2);
+                let b_ring = band(0.25, 0.55, 0.9);
+                let a_ring = band(0.62, 0.93, 0.6);
+                let encke_gap = 1.0 - band(0.855, 0.865, 1.0);
+                // ondas finas para que no se vea liso
+                let ripples = 0.85 + 0.15 * ((t * 180.0).sin() * (t * 47.0).cos());
+
+                ((c_ring + b_ring + a_ring) * encke_gap * ripples).clamp(0.0, 1.0)
+            })
+            .collect();
+
+        RingProfile { samples }
+    }
+
+    /// lee la primera fila de una imagen, el gris es la densidad (de adentro hacia afuera)
+    pub fn from_image(path: &str) -> image::ImageResult<Self> {
+        let image = image::open(path)?.to_luma8();
+        let samples = (0..image.width())
+            .map(|x| image.get_pixel(x, 0)[0] as f32 / 255.0)
+            .collect();
+
+        Ok(RingProfile { samples })
+    }
+
+    /// t va de 0 (borde de adentro) a 1 (borde de afuera)
+    pub fn density(&self, t: f32) -> f32 {
+        if !(0.0..=1.0).contains(&t) || self.samples.is_empty() {
+            return 0.0;
+        }
+        let x = t * (self.samples.len() - 1) as f32;
+        let i = (x as usize).min(self.samples.len() - 1);
+        let next = (i + 1).min(self.samples.len() - 1);
+        let f = x - i as f32;
+
+        self.samples[i] * (1.0 - f) + self.samples[next] * f
+    }
+}
+
+/// lo que necesita el shader del anillo; los radios van en espacio del modelo del anillo
+#[derive(Clone)]
+pub struct Rings {
+    pub profile: Rc<RingProfile>,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub planet_radius: f32,
+}
+
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_outside_the_ring() {
+        let profile = RingProfile::procedural(512);
+        // t < 0 es más adentro del radio interior, t > 1 más afuera del exterior
+        for t in [-0.5, -0.01, 1.01, 2.0] {
+            assert_eq!(profile.density(t), 0.0);
+        }
+        assert_eq!(RingProfile { samples: Vec::new() }.density(0.5), 0.0);
+    }
+
+    #[test]
+    fn gaps_are_thin_and_bands_are_dense() {
+        let profile = RingProfile::procedural(512);
+        let b_ring = profile.density(0.4);
+        let a_ring = profile.density(0.75);
+        assert!(b_ring > 0.6 && a_ring > 0.4);
+        // la división de Cassini queda vacía; Encke es tan angosto que solo baja a menos de la mitad
+        assert!(profile.density(0.585) < 0.05);
+        assert!(profile.density(0.86) < a_ring * 0.5);
+    }
+
+    #[test]
+    fn density_interpolates_between_samples() {
+        let profile = RingProfile { samples: vec![0.0, 1.0] };
+        assert!((profile.density(0.25) - 0.25).abs() < 1e-6);
+    }
+}
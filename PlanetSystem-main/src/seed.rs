@@ -0,0 +1,15 @@
+/// semilla propia de cada cosa que se genera, sacada de la semilla de la escena;
+/// así agregar algo nuevo no cambia lo que ya salía con la misma semilla
+pub fn derive(seed: u64, stream: &str) -> u64 {
+    // FNV-1a del nombre
+    let name = stream.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3));
+    splitmix(seed ^ name)
+}
+
+// revuelve los bits para que semillas parecidas den resultados distintos
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
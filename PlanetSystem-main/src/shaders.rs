@@ -1,18 +1,48 @@
-use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, dot, mat4_to_mat3};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, Mat4, dot, mat4_to_mat3};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragments::Fragments;
 use crate::color::Color;
+use crate::rings::{smoothstep, Rings};
+use crate::phong::BlinnPhong;
+use crate::pbr::{AlphaMode, PbrMaterial};
+use crate::normal_mapping;
+use std::rc::Rc;
 use std::f32::consts::PI;
 
-pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+// escala del hash sin(x) * k de todos los shaders; se escribe como siempre aunque f32 no lo guarde entero
+#[allow(clippy::excessive_precision)]
+const HASH_SCALE: f32 = 43758.5453;
+
+/// matrices que valen para todo un draw, se calculan una vez y no en cada vértice
+pub struct VertexTransform {
+  model_view_projection: Mat4,
+  viewport: Mat4,
+  // las tangentes van sobre la superficie, se transforman como posiciones y no como normales
+  model_matrix: Mat3,
+  normal_matrix: Mat3,
+}
+
+impl VertexTransform {
+  pub fn new(uniforms: &Uniforms) -> Self {
+    let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
+    VertexTransform {
+      model_view_projection: uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix,
+      viewport: uniforms.viewport_matrix,
+      model_matrix: model_mat3,
+      normal_matrix: model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity()),
+    }
+  }
+}
+
+pub fn vertex_shader(vertex: &Vertex, transform: &VertexTransform) -> Vertex {
   let position = Vec4::new(
     vertex.position.x,
     vertex.position.y,
     vertex.position.z,
     1.0
   );
-  let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
+  let transformed = transform.model_view_projection * position;
 
   let w = transformed.w;
   let ndc_position = Vec4::new(
@@ -22,23 +52,50 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     1.0
   );
 
-  let screen_position = uniforms.viewport_matrix * ndc_position;
+  let screen_position = transform.viewport * ndc_position;
 
-  let model_mat3 = mat4_to_mat3(&uniforms.model_matrix); 
-  let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
-
-  let transformed_normal = normal_matrix * vertex.normal;
+  let transformed_normal = transform.normal_matrix * vertex.normal;
+  let tangent = transform.model_matrix * vertex.tangent.xyz();
+  let transformed_tangent = Vec4::new(tangent.x, tangent.y, tangent.z, vertex.tangent.w);
 
   Vertex {
     position: vertex.position,
     normal: vertex.normal,
     tex_coords: vertex.tex_coords,
+    tangent: vertex.tangent,
     color: vertex.color,
     transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
     transformed_normal,
+    transformed_tangent,
   }
 }
 
+/// parámetros de la atmósfera de un planeta; los coeficientes van por radio de planeta
+#[derive(Debug, Clone, Copy)]
+pub struct Atmosphere {
+  pub shell_scale: f32,
+  pub density: f32,
+  pub rayleigh: Vec3,
+  pub mie: f32,
+  pub mie_g: f32,
+  pub sun_intensity: f32,
+}
+
+impl Atmosphere {
+  /// como la de la Tierra pero más gruesa para que se note de lejos
+  pub fn earth_like(density: f32) -> Self {
+    Atmosphere {
+      shell_scale: 1.15,
+      density,
+      rayleigh: Vec3::new(1.5, 3.6, 8.8),
+      mie: 2.1,
+      mie_g: 0.76,
+      sun_intensity: 20.0,
+    }
+  }
+}
+
+#[derive(Clone)]
 pub enum ShaderType {
   Sun,
   Earth,
@@ -48,8 +105,21 @@ pub enum ShaderType {
   IcyPlanet,
   VolcanicPlanet,
   Moon,
-  Ring,
+  Ring(Rings),
   Ship, 
+  BlinnPhong(Rc<BlinnPhong>),
+  Pbr(Rc<PbrMaterial>),
+  Asteroid,
+  EngineGlow,
+  Atmosphere(Atmosphere),
+}
+
+/// alpha test antes de la profundidad, así lo recortado no tapa nada
+pub fn discards(fragment: &Fragments, current_shader: &ShaderType) -> bool {
+  match current_shader {
+    ShaderType::Pbr(material) => material.cuts_out(fragment.tex_coords),
+    _ => false,
+  }
 }
 
 pub fn fragment_shader(fragment: &Fragments, uniforms: &Uniforms, current_shader: &ShaderType) -> Color {
@@ -62,8 +132,13 @@ pub fn fragment_shader(fragment: &Fragments, uniforms: &Uniforms, current_shader
     ShaderType::IcyPlanet => icy_planet_shader(fragment, uniforms),
     ShaderType::VolcanicPlanet => volcanic_planet_shader(fragment, uniforms),
     ShaderType::Moon => moon_shader(fragment, uniforms),
-    ShaderType::Ring => ring_shader(fragment, uniforms),
-    ShaderType::Ship => ship_shader(fragment, uniforms)
+    ShaderType::Ring(rings) => ring_shader(fragment, uniforms, rings),
+    ShaderType::Ship => ship_shader(fragment, uniforms),
+    ShaderType::BlinnPhong(material) => blinn_phong_shader(fragment, uniforms, material),
+    ShaderType::Pbr(material) => pbr_shader(fragment, uniforms, material),
+    ShaderType::Asteroid => asteroid_shader(fragment, uniforms),
+    ShaderType::EngineGlow => engine_glow_shader(fragment, uniforms),
+    ShaderType::Atmosphere(atmosphere) => atmosphere_shader(fragment, uniforms, atmosphere),
   }
 }
 
@@ -214,7 +289,7 @@ pub fn gas_planet_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
 }
 
 // planet del rocoso
-pub fn rocky_planet_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
+pub fn rocky_planet_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
 
   let base_color = Color::new(139, 69, 19);    // café rojo
   let mid_color = Color::new(205, 92, 92);     // rojo rosado
@@ -227,7 +302,7 @@ pub fn rocky_planet_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color
 
   let x = fragment.vertex_pos.x;
   let y = fragment.vertex_pos.y;
-  let randomness = (x * 12.9898 + y * 78.233).sin() * 43758.5453;
+  let randomness = (x * 12.9898 + y * 78.233).sin() * HASH_SCALE;
   let random_factor = randomness.fract() * detail_scale;
 
   
@@ -240,12 +315,15 @@ pub fn rocky_planet_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color
       mid_color.lerp(&highlight_color, (noise - 0.4) / 0.6)
   };
 
-  
-  rocky_surface * fragment.intensity
+  // relieve con la parte suave del mismo ruido, así las rocas claras quedan arriba
+  let height = |p: Vec3| ((p.x * rock_scale).sin() * (p.y * rock_scale).cos()).abs() * 0.06;
+  let normal = normal_mapping::bump_normal(fragment, &uniforms.model_matrix, height, 1.0);
+
+  rocky_surface * normal_mapping::intensity(normal)
 }
 
 // Una luna para el rocoso - try 2
-pub fn moon_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
+pub fn moon_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
   // base colors
   let base_color = Color::new(169, 169, 169);    // Gris
   let mid_color = Color::new(190, 190, 190);     // Gris medio
@@ -257,7 +335,7 @@ pub fn moon_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
   // coor
   let x = fragment.vertex_pos.x;
   let y = fragment.vertex_pos.y;
-  let randomness = (x * 15.789 + y * 41.233).sin() * 43758.5453;
+  let randomness = (x * 15.789 + y * 41.233).sin() * HASH_SCALE;
   let random_factor = randomness.fract() * detail_scale;
 
   let noise = (((x + random_factor) * rock_scale).sin() * ((y + random_factor) * rock_scale).cos()).abs();
@@ -269,29 +347,11 @@ pub fn moon_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
       mid_color.lerp(&highlight_color, (noise - 0.5) / 0.5)
   };
 
-  //  cráteres
-  let crater_positions = [
-      (0.1, 0.2, 0.50), 
-      (-0.3, -0.1, 0.30),
-      (0.4, -0.3, 0.2), 
-      (-0.1, 0.5, 0.40),
-      (-0.5, -0.4, 0.25),
-      (0.3, 0.4, 0.35),
-      (0.1, 0.5, 0.20),
-      (0.2, -0.1, 0.25),
-      (0.0, -0.6, 0.28), 
-      (-0.4, 0.2, 0.22),
-      (0.5, 0.0, 0.30),  
-      (-0.2, -0.5, 0.18), 
-      (0.35, 0.5, 0.24),
-      (-0.45, -0.3, 0.20),
-  ];
-
   let crater_color = Color::new(100, 100, 100); // Gris oscuro para los cráteres
 
   // intensity de los crat
   let mut combined_crater_intensity = 0.0;
-  for &(cx, cy, radius) in crater_positions.iter() {
+  for &(cx, cy, radius) in MOON_CRATERS.iter() {
       let distance = ((fragment.vertex_pos.x - cx).powi(2)
           + (fragment.vertex_pos.y - cy).powi(2))
           .sqrt();
@@ -302,8 +362,55 @@ pub fn moon_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
   //  intensity
   let final_surface = rocky_surface.lerp(&crater_color, combined_crater_intensity);
 
+  // los cráteres se hunden y el borde queda levantado, la luz lo marca
+  let normal = normal_mapping::bump_normal(fragment, &uniforms.model_matrix, moon_height, 1.0);
+
   // multiplu
-  final_surface * fragment.intensity
+  final_surface * normal_mapping::intensity(normal)
+}
+
+//  cráteres de la luna: centro en x, y del modelo y radio
+const MOON_CRATERS: [(f32, f32, f32); 14] = [
+    (0.1, 0.2, 0.50), 
+    (-0.3, -0.1, 0.30),
+    (0.4, -0.3, 0.2), 
+    (-0.1, 0.5, 0.40),
+    (-0.5, -0.4, 0.25),
+    (0.3, 0.4, 0.35),
+    (0.1, 0.5, 0.20),
+    (0.2, -0.1, 0.25),
+    (0.0, -0.6, 0.28), 
+    (-0.4, 0.2, 0.22),
+    (0.5, 0.0, 0.30),  
+    (-0.2, -0.5, 0.18), 
+    (0.35, 0.5, 0.24),
+    (-0.45, -0.3, 0.20),
+];
+
+// hondo en el centro de cada cráter con un borde levantado alrededor
+pub fn moon_height(p: Vec3) -> f32 {
+  MOON_CRATERS.iter()
+    .map(|&(cx, cy, radius)| {
+      let t = ((p.x - cx).powi(2) + (p.y - cy).powi(2)).sqrt() / radius;
+      let bowl = if t < 1.0 { (t * t - 1.0) * 0.12 * radius } else { 0.0 };
+      let rim = 0.04 * radius * (-((t - 1.0) / 0.2).powi(2)).exp();
+      bowl + rim
+    })
+    .sum()
+}
+
+// rocas del cinturón
+pub fn asteroid_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
+  let dark_color = Color::new(74, 66, 58);     // café grisáceo
+  let light_color = Color::new(140, 128, 112); // polvo claro
+
+  let x = fragment.vertex_pos.x;
+  let y = fragment.vertex_pos.y;
+  let z = fragment.vertex_pos.z;
+  let randomness = (x * 27.17 + y * 61.91 + z * 13.37).sin() * HASH_SCALE;
+  let noise = (((x * 6.0).sin() * (z * 6.0).cos()).abs() * 0.7 + randomness.fract() * 0.3).clamp(0.0, 1.0);
+
+  dark_color.lerp(&light_color, noise) * fragment.intensity
 }
 
 // mov de la luna
@@ -342,27 +449,50 @@ pub fn ring_planet_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
 }
 
 // los anillos de saturno viven
-fn ring_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
-  // Colores base para el anillo
-  let base_color = Color::new(255, 220, 80); // yellowstone
-  let shadow_color = Color::new(150, 120, 60); // 
-
-  let surface_color = base_color;
-
- 
-  let light_direction = Vec3::new(1.0, 1.0, 1.0).normalize(); 
-  let normal = fragment.vertex_pos.normalize(); 
-  let light_intensity = (normal.dot(&light_direction)).clamp(0.2, 1.0); 
-
-  
-  let final_color = match uniforms.debug_mode {
-      1 => base_color * fragment.intensity,                                                 
-      _ => surface_color * light_intensity + shadow_color * (1.0 - light_intensity),    
+// densidad por radio (huecos), translúcido, más brillante a contraluz y con la sombra del planeta
+fn ring_shader(fragment: &Fragments, uniforms: &Uniforms, rings: &Rings) -> Color {
+  let inner_color = Color::new(150, 130, 105); // café grisáceo adentro
+  let outer_color = Color::new(232, 214, 178); // crema afuera
+
+  let radius = (fragment.vertex_pos.x.powi(2) + fragment.vertex_pos.z.powi(2)).sqrt();
+  let t = (radius - rings.inner_radius) / (rings.outer_radius - rings.inner_radius);
+  let density = rings.profile.density(t);
+  if density <= 0.0 {
+    return Color::BLACK.with_alpha(0.0);
+  }
+  let base_color = inner_color.lerp(&outer_color, t);
+
+  // en mundo: punto del anillo, normal del plano y direcciones al sol y a la cámara
+  let world_pos = (uniforms.model_matrix * Vec4::new(fragment.vertex_pos.x, fragment.vertex_pos.y, fragment.vertex_pos.z, 1.0)).xyz();
+  let center = uniforms.model_matrix.column(3).xyz();
+  let scale = uniforms.model_matrix.column(0).xyz().magnitude();
+  let normal = uniforms.model_matrix.column(1).xyz().normalize();
+  let to_light = (uniforms.light_position - world_pos).normalize();
+  let to_camera = (uniforms.camera_position - world_pos).normalize();
+
+  let lit_side = to_light.dot(&normal).signum() == to_camera.dot(&normal).signum();
+  let brightness = if lit_side {
+    0.25 + 0.75 * to_light.dot(&normal).abs()
+  } else {
+    // a contraluz pasa la luz por las partes delgadas y se dispersa hacia adelante
+    let g = 0.6;
+    let cos_theta = (-to_light).dot(&to_camera);
+    let forward = (1.0 - g * g) / (1.0 + g * g - 2.0 * g * cos_theta).powf(1.5);
+    (density * (1.0 - density) * 4.0 * 0.3 * forward).min(1.5)
   };
 
-  final_color
-}
+  // sombra del planeta: el rayo hacia el sol choca con la esfera
+  let planet_radius = rings.planet_radius * scale;
+  let offset = world_pos - center;
+  let b = offset.dot(&to_light);
+  let closest = (offset - to_light * b).magnitude();
+  let shadow = if b < 0.0 && closest < planet_radius { 0.08 } else { 1.0 };
 
+  match uniforms.debug_mode {
+      1 => base_color * density,
+      _ => (base_color * (brightness * shadow)).with_alpha(density),
+  }
+}
 
 // movement
 pub fn planet_orbit(time: f32, radius: f32, speed: f32) -> Vec3 {
@@ -377,7 +507,7 @@ pub fn earth_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
   let z = fragment.vertex_pos.z;
 
   // emm coor
-  let theta = (y / 0.5).asin(); 
+  let theta = (y / 0.5).clamp(-1.0, 1.0).asin(); 
   let phi = z.atan2(x);        
   let u = (phi / (2.0 * PI)) + 0.5; 
   let v = (theta / PI) + 0.5;      
@@ -429,15 +559,259 @@ pub fn earth_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
       }
   }
 
-  // final color 
-  let final_color = if is_in_atmosphere {
-      
+  let surface = if is_in_atmosphere {
       base_color * (1.0 - cloud_intensity) + cloud_color_final
   } else {
       base_color
   };
+  let is_land = noise > continent_threshold;
+  let cloud_cover = (cloud_intensity + cloud_color_final.r as f32 / 255.0).min(1.0);
+
+  // de día y de noche según hacia dónde queda el sol, con una franja suave en el terminador
+  let world_pos = (uniforms.model_matrix * Vec4::new(x, y, z, 1.0)).xyz();
+  let normal = fragment.normal.normalize();
+  let to_light = (uniforms.light_position - world_pos).normalize();
+  let to_camera = (uniforms.camera_position - world_pos).normalize();
+  let sun = normal.dot(&to_light);
+  let day = smoothstep(-0.1, 0.15, sun);
+  let twilight = (-(sun / 0.12).powi(2)).exp();
+
+  // brillo del sol en el agua, las nubes lo tapan
+  let half_vector = (to_light + to_camera).normalize();
+  let glint = if is_land { 0.0 } else { normal.dot(&half_vector).max(0.0).powf(60.0) * (1.0 - cloud_cover) };
+
+  let lit = surface * (0.05 + 0.95 * sun.max(0.0))
+    + Color::new(255, 120, 50) * (twilight * 0.3)
+    + Color::new(255, 240, 210) * (glint * day * 0.8);
+
+  // ciudades en la tierra del lado oscuro, menos donde hay nubes
+  let cities = if is_land { city_lights(u, v) * (1.0 - cloud_cover * 0.7) } else { 0.0 };
+  let night = surface * 0.03 + Color::new(255, 190, 110) * cities;
 
-  final_color
+  match uniforms.debug_mode {
+      1 => surface,
+      2 => Color::new(255, 190, 110) * cities,
+      _ => night.lerp(&lit, day),
+  }
+}
+
+// luces de ciudad: puntitos al azar que se juntan donde hay "población", con un resplandor suave alrededor
+fn city_lights(u: f32, v: f32) -> f32 {
+  let cell_u = (u * 400.0).floor();
+  let cell_v = (v * 200.0).floor();
+  let random = ((cell_u * 12.9898 + cell_v * 78.233).sin() * HASH_SCALE).fract().abs();
+  let population = ((u * 23.0).sin() * (v * 17.0).cos() * 0.5 + 0.5).powf(2.0);
+
+  // cada ciudad es un punto que se apaga hacia el borde de su celda
+  let offset = Vec2::new((u * 400.0).fract() - 0.5, (v * 200.0).fract() - 0.5);
+  let dot = (1.0 - offset.norm() * 2.0).max(0.0);
+  let city = if random > 1.0 - population * 0.7 { dot * (0.6 + random * 0.4) } else { 0.0 };
+  (city + population * 0.12).min(1.0)
+}
+
+// brillo del motor, el alfa cae desde el centro del quad
+pub fn engine_glow_shader(fragment: &Fragments, uniforms: &Uniforms) -> Color {
+  let core_color = Color::new(255, 255, 255);
+  let flame_color = Color::new(90, 160, 255); // azulito
+
+  let distance = (fragment.vertex_pos.x.powi(2) + fragment.vertex_pos.y.powi(2)).sqrt() / 0.5;
+  let flicker = 0.85 + 0.15 * (uniforms.time as f32 * 0.7).sin();
+  let glow = (1.0 - distance).max(0.0).powf(2.0) * flicker;
+
+  flame_color.lerp(&core_color, glow).with_alpha(glow)
+}
+
+// cáscara de atmósfera: integra Rayleigh y Mie a lo largo del rayo que pasa por el fragmento
+pub fn atmosphere_shader(fragment: &Fragments, uniforms: &Uniforms, atmosphere: &Atmosphere) -> Color {
+  const VIEW_SAMPLES: usize = 8;
+  const LIGHT_SAMPLES: usize = 4;
+
+  // todo en unidades de radio del planeta, con el planeta en el origen
+  let center = uniforms.model_matrix.column(3).xyz();
+  let shell_radius = 0.5 * uniforms.model_matrix.column(0).xyz().magnitude();
+  let planet_radius = shell_radius / atmosphere.shell_scale;
+  let world_pos = uniforms.model_matrix * Vec4::new(fragment.vertex_pos.x, fragment.vertex_pos.y, fragment.vertex_pos.z, 1.0);
+
+  let eye = (uniforms.camera_position - center) / planet_radius;
+  let point = (world_pos.xyz() - center) / planet_radius;
+  let sun_dir = (uniforms.light_position - center).normalize();
+  let ray_dir = (point - eye).normalize();
+  let top = atmosphere.shell_scale;
+
+  let Some((near, far)) = ray_sphere(eye, ray_dir, top) else {
+    return Color::BLACK.with_alpha(0.0);
+  };
+  let near = near.max(0.0);
+  // si el rayo pega en el planeta se corta ahí
+  let far = match ray_sphere(eye, ray_dir, 1.0) {
+    Some((hit, _)) if hit > 0.0 => far.min(hit),
+    _ => far,
+  };
+  if far <= near {
+    return Color::BLACK.with_alpha(0.0);
+  }
+
+  let thickness = top - 1.0;
+  let rayleigh_height = thickness * 0.25;
+  let mie_height = thickness * 0.1;
+  let rayleigh = atmosphere.rayleigh * atmosphere.density;
+  let mie = atmosphere.mie * atmosphere.density;
+  let extinction = |depth_r: f32, depth_m: f32| -> Vec3 {
+    let tau = rayleigh * depth_r + Vec3::repeat(mie * 1.1 * depth_m);
+    Vec3::new((-tau.x).exp(), (-tau.y).exp(), (-tau.z).exp())
+  };
+
+  let step = (far - near) / VIEW_SAMPLES as f32;
+  let mut view_depth_r = 0.0;
+  let mut view_depth_m = 0.0;
+  let mut sum_r = Vec3::zeros();
+  let mut sum_m = Vec3::zeros();
+
+  for i in 0..VIEW_SAMPLES {
+    let sample = eye + ray_dir * (near + step * (i as f32 + 0.5));
+    let height = sample.magnitude() - 1.0;
+    let density_r = (-height / rayleigh_height).exp() * step;
+    let density_m = (-height / mie_height).exp() * step;
+    view_depth_r += density_r;
+    view_depth_m += density_m;
+
+    // camino hacia el sol; si lo tapa el planeta este punto está en sombra
+    if let Some((hit, _)) = ray_sphere(sample, sun_dir, 1.0) {
+      if hit > 0.0 {
+        continue;
+      }
+    }
+    let Some((_, exit)) = ray_sphere(sample, sun_dir, top) else { continue };
+    let light_step = exit / LIGHT_SAMPLES as f32;
+    let mut light_depth_r = 0.0;
+    let mut light_depth_m = 0.0;
+    for j in 0..LIGHT_SAMPLES {
+      let light_sample = sample + sun_dir * (light_step * (j as f32 + 0.5));
+      let light_height = (light_sample.magnitude() - 1.0).max(0.0);
+      light_depth_r += (-light_height / rayleigh_height).exp() * light_step;
+      light_depth_m += (-light_height / mie_height).exp() * light_step;
+    }
+
+    let attenuation = extinction(view_depth_r + light_depth_r, view_depth_m + light_depth_m);
+    sum_r += attenuation * density_r;
+    sum_m += attenuation * density_m;
+  }
+
+  // fases: Rayleigh es casi pareja, Mie se va hacia adelante (halo cerca del sol)
+  let mu = ray_dir.dot(&sun_dir);
+  let phase_r = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+  let g = atmosphere.mie_g;
+  let phase_m = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (1.0 + mu * mu))
+    / ((2.0 + g * g) * (1.0 + g * g - 2.0 * g * mu).powf(1.5));
+
+  let light = (sum_r.component_mul(&rayleigh) * phase_r + sum_m * mie * phase_m) * atmosphere.sun_intensity;
+  // exposición simple para no quemar el blanco
+  let tone = |c: f32| ((1.0 - (-c).exp()) * 255.0) as u8;
+
+  Color::new(tone(light.x), tone(light.y), tone(light.z))
+}
+
+// distancias de entrada y salida de un rayo a una esfera centrada en el origen
+fn ray_sphere(origin: Vec3, dir: Vec3, radius: f32) -> Option<(f32, f32)> {
+  let b = origin.dot(&dir);
+  let c = origin.dot(&origin) - radius * radius;
+  let discriminant = b * b - c;
+  if discriminant < 0.0 {
+    return None;
+  }
+  let root = discriminant.sqrt();
+  Some((-b - root, -b + root))
+}
+
+// materiales del .mtl: ambiente + difuso + especular con el vector medio + emisión, d como alpha
+fn blinn_phong_shader(fragment: &Fragments, uniforms: &Uniforms, material: &BlinnPhong) -> Color {
+  const AMBIENT_LIGHT: f32 = 0.1;
+  // luz tenue desde la cámara para que el lado que no ve al sol no quede negro
+  const FILL_LIGHT: f32 = 0.4;
+
+  let world_pos = (uniforms.model_matrix * Vec4::new(fragment.vertex_pos.x, fragment.vertex_pos.y, fragment.vertex_pos.z, 1.0)).xyz();
+  let to_light = (uniforms.light_position - world_pos).normalize();
+  let to_camera = (uniforms.camera_position - world_pos).normalize();
+  let normal = match &material.bump_map {
+    Some(texture) => normal_mapping::normal_from_map(fragment, texture, fragment.tex_coords, 1.0),
+    None => fragment.normal,
+  };
+  // las caras se ven de los dos lados, la normal siempre hacia la cámara
+  let normal = if fragment.normal.dot(&to_camera) < 0.0 { -normal } else { normal };
+
+  let diffuse_color = material.diffuse_at(fragment.tex_coords);
+  let diffuse = normal.dot(&to_light).max(0.0);
+  let half_vector = (to_light + to_camera).normalize();
+  let specular = if diffuse > 0.0 { normal.dot(&half_vector).max(0.0).powf(material.shininess) } else { 0.0 };
+  let fill = normal.dot(&to_camera).max(0.0) * FILL_LIGHT;
+
+  let rgb = material.ambient.component_mul(&diffuse_color) * AMBIENT_LIGHT
+    + diffuse_color * (diffuse + fill)
+    + material.specular * specular
+    + material.emissive;
+
+  let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u8;
+  match uniforms.debug_mode {
+      1 => Color::new(channel(diffuse_color.x), channel(diffuse_color.y), channel(diffuse_color.z)),
+      _ => Color::new(channel(rgb.x), channel(rgb.y), channel(rgb.z)).with_alpha(material.opacity),
+  }
+}
+
+// metallic-roughness de glTF: GGX, Smith y Fresnel de Schlick con el sol y una luz de relleno
+fn pbr_shader(fragment: &Fragments, uniforms: &Uniforms, material: &PbrMaterial) -> Color {
+  const AMBIENT_LIGHT: f32 = 0.08;
+  const FILL_LIGHT: f32 = 0.3;
+
+  // lo recortado por Mask ya se descartó antes del depth test
+  let base_color = material.base_color_at(fragment.tex_coords);
+  let albedo = base_color.xyz();
+  let (metallic, roughness) = material.metallic_roughness_at(fragment.tex_coords);
+  let roughness = roughness.clamp(0.04, 1.0);
+  // los no metales reflejan ~4%, los metales reflejan con su color
+  let f0 = Vec3::repeat(0.04).lerp(&albedo, metallic);
+
+  let world_pos = (uniforms.model_matrix * Vec4::new(fragment.vertex_pos.x, fragment.vertex_pos.y, fragment.vertex_pos.z, 1.0)).xyz();
+  let to_light = (uniforms.light_position - world_pos).normalize();
+  let to_camera = (uniforms.camera_position - world_pos).normalize();
+  let normal = match &material.normal_map {
+    Some(texture) => normal_mapping::normal_from_map(fragment, texture, fragment.tex_coords, material.normal_scale),
+    None => fragment.normal,
+  };
+  let normal = if material.double_sided && fragment.normal.dot(&to_camera) < 0.0 { -normal } else { normal };
+
+  // luz que sale hacia la cámara por una luz de intensidad 1 en esa dirección
+  let brdf = |light: Vec3| -> Vec3 {
+    let n_l = normal.dot(&light);
+    if n_l <= 0.0 {
+      return Vec3::zeros();
+    }
+    let half_vector = (light + to_camera).normalize();
+    let n_v = normal.dot(&to_camera).max(1e-4);
+    let n_h = normal.dot(&half_vector).max(0.0);
+    let h_v = half_vector.dot(&to_camera).max(0.0);
+
+    let alpha2 = roughness.powi(4);
+    let distribution = alpha2 / (PI * (n_h * n_h * (alpha2 - 1.0) + 1.0).powi(2));
+    let k = (roughness + 1.0).powi(2) / 8.0;
+    let geometry = n_v / (n_v * (1.0 - k) + k) * n_l / (n_l * (1.0 - k) + k);
+    let fresnel = f0 + (Vec3::repeat(1.0) - f0) * (1.0 - h_v).powi(5);
+
+    let specular = fresnel * (distribution * geometry / (4.0 * n_v * n_l));
+    let diffuse = (Vec3::repeat(1.0) - fresnel).component_mul(&albedo) * (1.0 - metallic) / PI;
+    (diffuse + specular) * n_l * PI
+  };
+
+  let rgb = brdf(to_light)
+    + brdf(to_camera) * FILL_LIGHT
+    + (albedo * (1.0 - metallic) + f0) * AMBIENT_LIGHT
+    + material.emissive_at(fragment.tex_coords);
+
+  let alpha = if material.alpha_mode == AlphaMode::Blend { base_color.w } else { 1.0 };
+  let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u8;
+  match uniforms.debug_mode {
+      1 => Color::new(channel(albedo.x), channel(albedo.y), channel(albedo.z)),
+      _ => Color::new(channel(rgb.x), channel(rgb.y), channel(rgb.z)).with_alpha(alpha),
+  }
 }
 
 pub fn ship_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
@@ -446,6 +820,7 @@ pub fn ship_shader(fragment: &Fragments, _uniforms: &Uniforms) -> Color {
         r: (255.0 * scalar).clamp(51.0, 123.0) as u8,
         g: (255.0 * scalar).clamp(29.0, 70.0) as u8,
         b: (255.0 * scalar).clamp(64.0, 155.0) as u8,
+        a: 255,
     }
 }
 
@@ -0,0 +1,185 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use std::collections::HashMap;
+use crate::skybox::celestial_direction;
+use crate::{Framebuffer, Uniforms};
+
+// inclinación de la eclíptica sobre el ecuador celeste
+const OBLIQUITY: f32 = 23.44;
+// cada cuántos grados se corta un círculo en segmentos
+const ARC_STEP: f32 = 2.0;
+// más cerca de 90° de la vista la proyección se dispara, esos tramos ni se intentan
+const MIN_FORWARD: f32 = 0.05;
+// como las estrellas: detrás de todo lo demás
+const SKY_DEPTH: f32 = 1000.0;
+
+const CONSTELLATION_COLOR: u32 = 0x30507A;
+const GRID_COLOR: u32 = 0x202A38;
+const EQUATOR_COLOR: u32 = 0x6A2A2A;
+const ECLIPTIC_COLOR: u32 = 0x6A6020;
+
+/// ayudas para clases encima del cielo, se prenden con teclas
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SkyOverlays {
+    pub constellations: bool,
+    // meridianos cada 2h de ascensión recta y paralelos cada 30° de declinación
+    pub grid: bool,
+    pub equator: bool,
+    pub ecliptic: bool,
+}
+
+impl SkyOverlays {
+    pub fn any(&self) -> bool {
+        self.constellations || self.grid || self.equator || self.ecliptic
+    }
+}
+
+/// figuras de constelaciones de un .fab de Stellarium: "Ori 16 26727 26207 ..." con pares de
+/// números HIP; los pares con estrellas que no están en el catálogo se saltan
+pub fn parse_constellations(text: &str, stars_by_hip: &HashMap<u32, Vec3>) -> Result<Vec<(Vec3, Vec3)>, String> {
+    let mut segments = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let name = fields.next().unwrap_or_default();
+        let count: usize = fields.next().and_then(|count| count.parse().ok())
+            .ok_or(format!("línea {}: falta la cantidad de segmentos de {}", number + 1, name))?;
+        let ids: Vec<u32> = fields.map(|id| id.parse().map_err(|_| format!("línea {}: número HIP inválido {}", number + 1, id)))
+            .collect::<Result<_, _>>()?;
+        if ids.len() != count * 2 {
+            return Err(format!("línea {}: {} dice {} segmentos y trae {} estrellas", number + 1, name, count, ids.len()));
+        }
+
+        segments.extend(ids.chunks_exact(2).filter_map(|pair| Some((*stars_by_hip.get(&pair[0])?, *stars_by_hip.get(&pair[1])?))));
+    }
+    Ok(segments)
+}
+
+/// dibuja las ayudas prendidas; constellations son pares de direcciones
+pub fn draw(framebuffer: &mut Framebuffer, uniforms: &Uniforms, overlays: &SkyOverlays, constellations: &[(Vec3, Vec3)]) {
+    // solo la rotación de la vista, el cielo no se mueve con la cámara
+    let mut view = uniforms.view_matrix;
+    view[(0, 3)] = 0.0;
+    view[(1, 3)] = 0.0;
+    view[(2, 3)] = 0.0;
+    let sky = Sky { clip: uniforms.projection_matrix * view, viewport: uniforms.viewport_matrix };
+
+    if overlays.grid {
+        for hour in (0..24).step_by(2) {
+            sky.arc(framebuffer, GRID_COLOR, -90.0, 90.0, |dec| celestial_direction(hour as f32, dec));
+        }
+        for dec in [-60.0, -30.0, 30.0, 60.0] {
+            sky.arc(framebuffer, GRID_COLOR, 0.0, 360.0, |ra| celestial_direction(ra / 15.0, dec));
+        }
+    }
+    if overlays.equator {
+        sky.arc(framebuffer, EQUATOR_COLOR, 0.0, 360.0, |ra| celestial_direction(ra / 15.0, 0.0));
+    }
+    if overlays.ecliptic {
+        // el ecuador girado sobre el eje del equinoccio (+X): a las 6h sube hasta la oblicuidad
+        let (sin, cos) = OBLIQUITY.to_radians().sin_cos();
+        sky.arc(framebuffer, ECLIPTIC_COLOR, 0.0, 360.0, |longitude| {
+            let (s, c) = longitude.to_radians().sin_cos();
+            Vec3::new(c, s * sin, -s * cos)
+        });
+    }
+    if overlays.constellations {
+        for &(a, b) in constellations {
+            // arco de círculo máximo entre las dos estrellas
+            let angle = a.dot(&b).clamp(-1.0, 1.0).acos().to_degrees();
+            sky.arc(framebuffer, CONSTELLATION_COLOR, 0.0, angle, |t| a.slerp(&b, t / angle.max(1e-6)));
+        }
+    }
+}
+
+// matrices para pasar una dirección del cielo a la pantalla
+struct Sky {
+    clip: nalgebra_glm::Mat4,
+    viewport: nalgebra_glm::Mat4,
+}
+
+impl Sky {
+    fn project(&self, direction: Vec3) -> Option<Vec2> {
+        let clip = self.clip * Vec4::new(direction.x, direction.y, direction.z, 1.0);
+        if clip.w < MIN_FORWARD {
+            return None;
+        }
+        let ndc = clip / clip.w;
+        let screen = self.viewport * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+        Some(Vec2::new(screen.x, screen.y))
+    }
+
+    /// curva de point(t) con t de start a end (en grados), en tramos rectos de ARC_STEP
+    fn arc(&self, framebuffer: &mut Framebuffer, color: u32, start: f32, end: f32, point: impl Fn(f32) -> Vec3) {
+        framebuffer.set_current_color(color);
+        let steps = ((end - start) / ARC_STEP).ceil().max(1.0) as usize;
+        let mut previous = self.project(point(start));
+        for step in 1..=steps {
+            let t = start + (end - start) * step as f32 / steps as f32;
+            let current = self.project(point(t));
+            if let (Some(a), Some(b)) = (previous, current) {
+                draw_segment(framebuffer, a, b);
+            }
+            previous = current;
+        }
+    }
+}
+
+// línea de un pixel de ancho, recortada a la pantalla antes de recorrerla
+fn draw_segment(framebuffer: &mut Framebuffer, a: Vec2, b: Vec2) {
+    let Some((a, b)) = clip_to_screen(a, b, framebuffer.width as f32, framebuffer.height as f32) else {
+        return;
+    };
+    let steps = (b - a).abs().max().ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let p = a.lerp(&b, step as f32 / steps as f32);
+        framebuffer.point_additive(p.x as usize, p.y as usize, SKY_DEPTH);
+    }
+}
+
+// Liang-Barsky contra [0, width) x [0, height)
+fn clip_to_screen(a: Vec2, b: Vec2, width: f32, height: f32) -> Option<(Vec2, Vec2)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+    let edges = [(-d.x, a.x), (d.x, width - 1.0 - a.x), (-d.y, a.y), (d.y, height - 1.0 - a.y)];
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    (t0 <= t1).then(|| (a + d * t0, a + d * t1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constellation_pairs_resolve_by_hip() {
+        let stars = HashMap::from([(1, Vec3::x()), (2, Vec3::y()), (3, Vec3::z())]);
+        let fab = "# comentario\nAbc 2 1 2 2 3\n\nXyz 1 3 99\n";
+        let segments = parse_constellations(fab, &stars).unwrap();
+        // el par con la 99 no está en el catálogo
+        assert_eq!(segments, vec![(Vec3::x(), Vec3::y()), (Vec3::y(), Vec3::z())]);
+
+        assert!(parse_constellations("Abc 2 1 2 3", &stars).is_err());
+    }
+
+    #[test]
+    fn segments_are_clipped_to_the_screen() {
+        let (a, b) = clip_to_screen(Vec2::new(-100.0, 50.0), Vec2::new(300.0, 50.0), 200.0, 100.0).unwrap();
+        assert_eq!((a.x, b.x), (0.0, 199.0));
+        assert!(clip_to_screen(Vec2::new(-10.0, -10.0), Vec2::new(-5.0, 50.0), 200.0, 100.0).is_none());
+    }
+}
@@ -1,62 +1,211 @@
 use nalgebra_glm::{Vec3, Vec4};
-use rand::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use crate::cubemap::Cubemap;
+use crate::texture::{equirectangular_uv, Texture};
+use crate::framebuffer::DepthPass;
+use crate::sky_overlays::{self, SkyOverlays};
 use crate::{Framebuffer, Uniforms};
 
+// las más débiles que se ven a simple vista
+const NAKED_EYE_MAGNITUDE: f32 = 6.5;
+// de aquí para arriba el brillo ya es el máximo
+const BRIGHTEST_MAGNITUDE: f32 = 0.0;
+
 pub struct Skybox {
     stars: Vec<Star>,
+    // nebulosas y vía láctea detrás de las estrellas
+    background: Option<Background>,
+    // figuras de las constelaciones como pares de direcciones
+    constellations: Vec<(Vec3, Vec3)>,
+    pub overlays: SkyOverlays,
+}
+
+/// imagen de fondo del cielo, se lee con la dirección en que mira cada pixel
+pub enum Background {
+    Cubemap(Cubemap),
+    // panorama equirectangular con el norte arriba
+    Equirectangular(Texture),
+}
+
+impl Background {
+    /// cubemap en base/px.png, nx.png, ... o si no un panorama en base.png
+    pub fn load(base: &str) -> Option<Self> {
+        let faces = ["px", "nx", "py", "ny", "pz", "nz"].map(|face| format!("{}/{}.png", base, face));
+        if let Ok(cubemap) = Cubemap::load(faces.each_ref().map(String::as_str)) {
+            return Some(Background::Cubemap(cubemap));
+        }
+        Texture::load(&format!("{}.png", base)).ok().map(Background::Equirectangular)
+    }
+
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        match self {
+            Background::Cubemap(cubemap) => cubemap.sample(direction),
+            Background::Equirectangular(texture) => texture.sample(equirectangular_uv(direction)),
+        }
+    }
 }
 
 struct Star {
     position: Vec3,
     brightness: f32,
+    // de 0 a 1 por canal
+    color: Vec3,
     size: u8,
+    // número del catálogo Hipparcos, con él se arman las constelaciones
+    hip: Option<u32>,
 }
 
 impl Star {
-    /// estrella con propiedades aleatorias
-    pub fn new(radius: f32) -> Self {
-        let mut rng = rand::thread_rng();
-        let theta = rng.gen::<f32>() * 2.0 * PI; 
-        let phi = rng.gen::<f32>() * PI ;       
-
-        // coord cartesianas
-        let x = radius * phi.sin() * theta.cos();
-        let y = radius * phi.cos() ;
-        let z = radius * phi.sin() * theta.sin();
+    /// estrella con propiedades aleatorias, repartida parejo sobre la esfera
+    fn random(rng: &mut ChaCha8Rng, radius: f32) -> Self {
+        // con la altura pareja (y no el ángulo) no se amontonan en los polos
+        let y: f32 = rng.gen_range(-1.0..=1.0);
+        let theta = rng.gen::<f32>() * 2.0 * PI;
+        let ring = (1.0 - y * y).sqrt();
 
         Star {
-            position: Vec3::new(x, y, z),
+            position: Vec3::new(ring * theta.cos(), y, ring * theta.sin()) * radius,
             // brillo
-            brightness: rng.gen::<f32>(), 
-            size: rng.gen_range(1..=3),  
+            brightness: rng.gen::<f32>(),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            size: rng.gen_range(1..=3),
+            hip: None,
+        }
+    }
+
+    /// estrella de catálogo: ascensión recta en horas, declinación en grados
+    fn from_catalogue(ra: f32, dec: f32, magnitude: f32, color_index: f32, hip: Option<u32>, radius: f32) -> Self {
+        // la magnitud ya es logarítmica, se reparte lineal entre la más brillante y la más débil
+        let t = ((NAKED_EYE_MAGNITUDE - magnitude) / (NAKED_EYE_MAGNITUDE - BRIGHTEST_MAGNITUDE)).clamp(0.0, 1.0);
+        let size = if magnitude < 1.5 { 3 } else if magnitude < 3.5 { 2 } else { 1 };
+
+        Star {
+            position: celestial_direction(ra, dec) * radius,
+            brightness: 0.15 + 0.85 * t,
+            color: color_from_index(color_index),
+            size,
+            hip,
         }
     }
 }
 
+/// dirección en el cielo con el polo norte celeste en +Y y las 0h de ascensión recta en +X;
+/// las 6h quedan en -Z para que visto desde adentro el este quede a la izquierda, como en el cielo
+pub fn celestial_direction(ra_hours: f32, dec_degrees: f32) -> Vec3 {
+    let ra = ra_hours / 24.0 * 2.0 * PI;
+    let dec = dec_degrees.to_radians();
+    Vec3::new(dec.cos() * ra.cos(), dec.sin(), -dec.cos() * ra.sin())
+}
+
+// índice B-V a color: azul las calientes, naranja las frías
+fn color_from_index(color_index: f32) -> Vec3 {
+    const STOPS: [(f32, [f32; 3]); 6] = [
+        (-0.4, [0.61, 0.69, 1.0]),
+        (0.0, [0.79, 0.84, 1.0]),
+        (0.4, [1.0, 0.98, 0.97]),
+        (0.8, [1.0, 0.93, 0.82]),
+        (1.2, [1.0, 0.82, 0.63]),
+        (2.0, [1.0, 0.7, 0.45]),
+    ];
+    let color_index = color_index.clamp(STOPS[0].0, STOPS[STOPS.len() - 1].0);
+    let i = STOPS.iter().rposition(|&(index, _)| index <= color_index).unwrap_or(0).min(STOPS.len() - 2);
+    let (start, a) = STOPS[i];
+    let (end, b) = STOPS[i + 1];
+    let t = (color_index - start) / (end - start);
+    Vec3::from(a).lerp(&Vec3::from(b), t)
+}
+
 impl Skybox {
-    ///  sky con un número específico de estrellas
-    pub fn new(star_count: usize, radius: f32) -> Self {
+    /// sky con un número específico de estrellas al azar; la misma semilla da el mismo cielo
+    pub fn random(star_count: usize, radius: f32, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
         let stars = (0..star_count)
-            .map(|_| Star::new(radius))
+            .map(|_| Star::random(&mut rng, radius))
             .collect();
-        Skybox { stars }
+        Skybox { stars, background: None, constellations: Vec::new(), overlays: SkyOverlays::default() }
+    }
+
+    /// CSV tipo HYG: columnas ra (horas), dec (grados), mag y ci (B-V); solo las que se ven a simple vista
+    pub fn from_catalogue(path: &str, radius: f32) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let stars = parse_catalogue(&text, radius).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(Skybox { stars, background: None, constellations: Vec::new(), overlays: SkyOverlays::default() })
+    }
+
+    /// figuras de un .fab de Stellarium; necesita las estrellas del catálogo con su número HIP
+    pub fn load_constellations(&mut self, path: &str) -> io::Result<()> {
+        let text = fs::read_to_string(path)?;
+        let stars_by_hip: HashMap<u32, Vec3> = self.stars.iter()
+            .filter_map(|star| Some((star.hip?, star.position.normalize())))
+            .collect();
+        self.constellations = sky_overlays::parse_constellations(&text, &stars_by_hip)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(())
+    }
+
+    pub fn with_background(self, background: Option<Background>) -> Self {
+        Skybox { background, ..self }
+    }
+
+    // el fondo en los pixeles que siguen vacíos, con el rayo de la cámara por cada uno
+    fn render_background(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, background: &Background) {
+        // en el pre-pass no se pinta color
+        if framebuffer.depth_pass == DepthPass::PrePass {
+            return;
+        }
+        // solo la rotación de la vista: el cielo está infinitamente lejos
+        let mut view = uniforms.view_matrix;
+        view[(0, 3)] = 0.0;
+        view[(1, 3)] = 0.0;
+        view[(2, 3)] = 0.0;
+        let Some(inverse) = (uniforms.projection_matrix * view).try_inverse() else {
+            return;
+        };
+
+        // el punto en el plano lejano es lineal en la pantalla, se avanza sumando
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        let far_point = |x: f32, y: f32| inverse * Vec4::new(x / width * 2.0 - 1.0, 1.0 - y / height * 2.0, 1.0, 1.0);
+        let origin = far_point(0.5, 0.5);
+        let step_x = far_point(1.5, 0.5) - origin;
+        let step_y = far_point(0.5, 1.5) - origin;
+
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u32;
+        for y in 0..framebuffer.height {
+            let row = origin + step_y * y as f32;
+            for x in 0..framebuffer.width {
+                framebuffer.paint_behind(x, y, || {
+                    let point = row + step_x * x as f32;
+                    let color = background.sample(point.xyz() / point.w);
+                    channel(color.x) << 16 | channel(color.y) << 8 | channel(color.z)
+                });
+            }
+        }
     }
 
     /// sky a frame
     pub fn render_sb(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3) {
+    if let Some(background) = &self.background {
+        self.render_background(framebuffer, uniforms, background);
+    }
+
     #[inline]
     fn put(framebuffer: &mut Framebuffer, x: i32, y: i32, depth: f32) {
         if x >= 0 && y >= 0
             && (x as usize) < framebuffer.width
             && (y as usize) < framebuffer.height
         {
-            framebuffer.point(x as usize, y as usize, depth);
+            // se suman para que se vean encima del fondo
+            framebuffer.point_additive(x as usize, y as usize, depth);
         }
     }
 
     for star in &self.stars {
-        
+
         let position = star.position + camera_position;
 
         // poroy
@@ -68,16 +217,16 @@ impl Skybox {
 
         let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
 
-        
+
         if screen_pos.z < 0.0 { continue; }
 
-        
+
         let x = screen_pos.x.round() as i32;
         let y = screen_pos.y.round() as i32;
 
         // color por brillo
-        let intensity = (star.brightness * 255.0).clamp(0.0, 255.0) as u8;
-        let color = (intensity as u32) << 16 | (intensity as u32) << 8 | intensity as u32;
+        let channel = |c: f32| ((c * star.brightness).clamp(0.0, 1.0) * 255.0) as u32;
+        let color = channel(star.color.x) << 16 | channel(star.color.y) << 8 | channel(star.color.z);
         framebuffer.set_current_color(color);
 
 
@@ -99,6 +248,103 @@ impl Skybox {
             _ => {}
         }
     }
+
+    // las líneas van después para no tapar las estrellas
+    if self.overlays.any() {
+        sky_overlays::draw(framebuffer, uniforms, &self.overlays, &self.constellations);
+    }
 }
 
 }
+
+// las columnas se buscan por nombre en el encabezado; las filas incompletas se saltan
+fn parse_catalogue(text: &str, radius: f32) -> Result<Vec<Star>, String> {
+    let mut lines = text.lines();
+    let header: Vec<String> = lines.next()
+        .ok_or("el catálogo está vacío")?
+        .split(',')
+        .map(|name| name.trim().trim_matches('"').to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|c| c == name).ok_or(format!("al catálogo le falta la columna {}", name));
+    let (ra, dec, mag) = (column("ra")?, column("dec")?, column("mag")?);
+    // sin color todas salen blancas
+    let ci = column("ci").ok();
+    let hip = column("hip").ok();
+
+    let stars: Vec<Star> = lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim().trim_matches('"')).collect();
+            let number = |i: usize| fields.get(i)?.parse::<f32>().ok();
+            let magnitude = number(mag)?;
+            // el sol viene en el catálogo de HYG con magnitud -26
+            if !(-2.0..=NAKED_EYE_MAGNITUDE).contains(&magnitude) {
+                return None;
+            }
+            let color_index = ci.and_then(number).unwrap_or(0.4);
+            let hip = hip.and_then(|i| fields.get(i)?.parse().ok());
+            Some(Star::from_catalogue(number(ra)?, number(dec)?, magnitude, color_index, hip, radius))
+        })
+        .collect();
+
+    if stars.is_empty() {
+        return Err("el catálogo no tiene estrellas visibles".to_string());
+    }
+    Ok(stars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_catalogue_columns_by_name() {
+        let csv = "id,proper,ra,dec,mag,ci\n\
+                   0,Sol,0.0,0.0,-26.7,0.656\n\
+                   1,Polaris,2.53,89.26,1.97,0.636\n\
+                   2,,6.0,0.0,5.0,-0.3\n\
+                   3,,1.0,1.0,12.0,1.0\n\
+                   4,,bad,1.0,3.0,1.0\n";
+        let stars = parse_catalogue(csv, 1.0).unwrap();
+        // se quedan Polaris y la de magnitud 5
+        assert_eq!(stars.len(), 2);
+        assert!(stars[0].position.y > 0.99);
+        assert!((stars[1].position + Vec3::z()).magnitude() < 1e-5);
+        assert!(stars[0].brightness > stars[1].brightness);
+        // la de B-V negativo es azulada
+        assert!(stars[1].color.z > stars[1].color.x);
+    }
+
+    #[test]
+    fn right_ascension_turns_counterclockwise_around_the_pole() {
+        // como en la esfera celeste: 0h x 6h da el polo norte, si no el cielo sale al revés
+        let turn = celestial_direction(0.0, 0.0).cross(&celestial_direction(6.0, 0.0));
+        assert!((turn - celestial_direction(0.0, 90.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn random_stars_are_uniform_and_repeatable() {
+        let sky = Skybox::random(20000, 1.0, 7);
+        // en una esfera pareja cada banda de la misma altura tiene la misma área
+        let polar = sky.stars.iter().filter(|star| star.position.y.abs() > 0.5).count();
+        assert!((polar as f32 / 20000.0 - 0.5).abs() < 0.02);
+
+        let again = Skybox::random(20000, 1.0, 7);
+        assert!(sky.stars.iter().zip(&again.stars).all(|(a, b)| a.position == b.position));
+    }
+
+    #[test]
+    fn random_sky_for_a_seed_does_not_change() {
+        // si esto cambia, cambió el cielo de todas las semillas guardadas
+        let sky = Skybox::random(3, 1.0, 7);
+        let expected = [
+            (Vec3::new(0.37993166, -0.71999335, 0.58074224), 0.18205506, 1),
+            (Vec3::new(-0.2516232, -0.45982116, -0.8516162), 0.036105752, 3),
+            (Vec3::new(-0.41359234, -0.85767055, -0.30552024), 0.8109435, 2),
+        ];
+        for (star, (position, brightness, size)) in sky.stars.iter().zip(expected) {
+            assert!((star.position - position).magnitude() < 1e-5);
+            assert!((star.brightness - brightness).abs() < 1e-6);
+            assert_eq!(star.size, size);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::Instant;
+
+/// pasadas del frame en el orden en que se corren, cada una una sola vez. el cielo va después
+/// de lo opaco y no primero como dice el orden original: paint_behind solo llena las muestras
+/// que quedaron vacías, así el cielo tapado por planetas ni se sombrea
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    Opaque,
+    Sky,
+    Rings,
+    Transparent,
+    // órbitas encima de todo lo de la escena
+    Overlays,
+    // resolver muestras, SSAA y FXAA
+    Post,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 6] = [Pass::Opaque, Pass::Sky, Pass::Rings, Pass::Transparent, Pass::Overlays, Pass::Post];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Pass::Opaque => "OPAQUE",
+            Pass::Sky => "SKY",
+            Pass::Rings => "RINGS",
+            Pass::Transparent => "TRANSP",
+            Pass::Overlays => "OVERLAY",
+            Pass::Post => "POST",
+        }
+    }
+}
+
+/// contadores de un frame, se reinician al empezar cada uno
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameStats {
+    pub objects_drawn: u32,
+    pub objects_culled: u32,
+    pub frame_ms: f32,
+    // cuánto del frame se fue en recorrer triángulos
+    pub raster_ms: f32,
+    // vertex shader corrido vs esquinas de triángulos, la diferencia la ahorra el cache
+    pub vertices_shaded: u32,
+    pub vertices_referenced: u32,
+    pub fragments_rasterized: u32,
+    // tapados por el early-z, nunca llegaron al shader
+    pub fragments_rejected: u32,
+    pub fragments_shaded: u32,
+    // pixeles de la imagen, para sacar el overdraw
+    pub pixels: u32,
+    // milisegundos de cada pasada, en el orden de Pass::ALL
+    pub pass_ms: [f32; Pass::ALL.len()],
+}
+
+impl FrameStats {
+    pub fn reset(&mut self) {
+        *self = FrameStats::default();
+    }
+
+    /// suma a la pasada lo que pasó desde start (el pre-pass cuenta como opaco)
+    pub fn end_pass(&mut self, pass: Pass, start: Instant) {
+        self.pass_ms[pass as usize] += start.elapsed().as_secs_f32() * 1000.0;
+    }
+
+    /// "OPAQUE 30.1 SKY 2.0 ..." para el HUD y el benchmark
+    pub fn passes_line(&self) -> String {
+        Pass::ALL.iter()
+            .map(|&pass| format!("{} {:.1}", pass.label(), self.pass_ms[pass as usize]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// fragmentos sombreados por pixel de la imagen (1 sería sin nada repetido ni fondo)
+    pub fn overdraw(&self) -> f32 {
+        if self.pixels == 0 { 0.0 } else { self.fragments_shaded as f32 / self.pixels as f32 }
+    }
+
+    /// texto que se pinta en la esquina
+    pub fn lines(&self) -> Vec<String> {
+        let fps = if self.frame_ms > 0.0 { 1000.0 / self.frame_ms } else { 0.0 };
+        vec![
+            format!("FPS {:.0} ({:.1} MS)", fps, self.frame_ms),
+            format!("RASTER {:.1} MS", self.raster_ms),
+            self.passes_line(),
+            format!("DRAWN {}", self.objects_drawn),
+            format!("CULLED {}", self.objects_culled),
+            format!("VERTS {} OF {}", self.vertices_shaded, self.vertices_referenced),
+            format!("FRAGS {} EARLY-Z {}", self.fragments_rasterized, self.fragments_rejected),
+            format!("SHADED {} OVERDRAW {:.2}", self.fragments_shaded, self.overdraw()),
+        ]
+    }
+}
+
+const GLYPH_SCALE: usize = 2;
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// dibuja las líneas arriba a la izquierda sobre la imagen final (ya sin AA)
+pub fn draw_overlay(buffer: &mut [u32], width: usize, height: usize, lines: &[String], color: u32) {
+    for (row, line) in lines.iter().enumerate() {
+        let top = 8 + row * (GLYPH_HEIGHT + 2) * GLYPH_SCALE;
+        for (column, c) in line.chars().enumerate() {
+            let left = 8 + column * (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+            for (gy, bits) in glyph(c).iter().enumerate() {
+                for gx in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> gx) == 0 {
+                        continue;
+                    }
+                    for sy in 0..GLYPH_SCALE {
+                        for sx in 0..GLYPH_SCALE {
+                            let (x, y) = (left + gx * GLYPH_SCALE + sx, top + gy * GLYPH_SCALE + sy);
+                            if x < width && y < height {
+                                buffer[y * width + x] = color;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// fuente de 3x5, cada fila son 3 bits de izquierda a derecha
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
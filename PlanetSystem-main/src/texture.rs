@@ -0,0 +1,78 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use std::f32::consts::PI;
+
+/// imagen en memoria con colores rgba de 0 a 1, se lee con uv
+pub struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec4>,
+}
+
+impl Texture {
+    /// pixels opacos por filas, de arriba hacia abajo
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        Texture::from_rgba(width, height, pixels.iter().map(|pixel| pixel.push(1.0)).collect())
+    }
+
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<Vec4>) -> Self {
+        Texture { width, height, pixels }
+    }
+
+    pub fn load(path: &str) -> image::ImageResult<Self> {
+        let image = image::open(path)?.to_rgba8();
+        let pixels = image
+            .pixels()
+            .map(|pixel| Vec4::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32, pixel[3] as f32) / 255.0)
+            .collect();
+
+        Ok(Texture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels,
+        })
+    }
+
+    /// bilineal, la uv se repite fuera de [0, 1]
+    pub fn sample(&self, uv: Vec2) -> Vec3 {
+        self.sample_rgba(uv).xyz()
+    }
+
+    /// como sample pero con el alpha
+    pub fn sample_rgba(&self, uv: Vec2) -> Vec4 {
+        self.bilinear(uv.x.rem_euclid(1.0), uv.y.rem_euclid(1.0), true)
+    }
+
+    /// bilineal sin dar la vuelta en los bordes, para las caras de un cubemap
+    pub fn sample_clamped(&self, uv: Vec2) -> Vec3 {
+        self.bilinear(uv.x.clamp(0.0, 1.0), uv.y.clamp(0.0, 1.0), false).xyz()
+    }
+
+    fn bilinear(&self, u: f32, v: f32, wrap: bool) -> Vec4 {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let texel = |x: f32, y: f32| {
+            let (x, y) = if wrap {
+                ((x as i64).rem_euclid(self.width as i64) as usize, (y as i64).rem_euclid(self.height as i64) as usize)
+            } else {
+                ((x.max(0.0) as usize).min(self.width - 1), (y.max(0.0) as usize).min(self.height - 1))
+            };
+            self.pixels[y * self.width + x]
+        };
+
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1.0, y0) * fx;
+        let bottom = texel(x0, y0 + 1.0) * (1.0 - fx) + texel(x0 + 1.0, y0 + 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// uv de una imagen equirectangular para una dirección: u da la vuelta alrededor de Y
+/// empezando en +X, v va del polo norte (arriba) al sur
+pub fn equirectangular_uv(direction: Vec3) -> Vec2 {
+    let d = direction.normalize();
+    let u = d.z.atan2(d.x).rem_euclid(2.0 * PI) / (2.0 * PI);
+    let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+    Vec2::new(u, v)
+}
@@ -1,76 +1,544 @@
 use nalgebra_glm::{Vec3, dot, Vec2};
-use crate::fragments::Fragments;
+use wide::{f32x4, i32x4, CmpGt, CmpLt};
+use crate::fragments::{Fragments, LIGHT_DIR};
+use crate::framebuffer::{Framebuffer, MAX_SAMPLES};
 use crate::vertex::Vertex;
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragments> {
-  let mut fragments = Vec::new();
-  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+// las posiciones en pantalla se redondean a 1/256 de pixel y se trabaja con enteros,
+// así dos triángulos que comparten un lado calculan exactamente lo mismo
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+// más allá de esto el triángulo está roto (detrás de la cámara) y los productos no caben
+const GUARD_BAND: f32 = (1 << 20) as f32;
+// hasta este tamaño en pixeles los lados caben en i32 y se puede usar SIMD: el valor de un lado
+// llega a 2·(tamaño + el quad de más)²·256², tiene que quedar abajo de 2^31 (≈128 px)
+const SIMD_MAX_EXTENT: i64 = 96;
 
-  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+// margen del descarte por depth dentro del rasterizador
+const EARLY_Z_SLACK: f32 = 1e-4;
 
-  let light_dir = Vec3::new(0.0, 0.0, 1.0);
+// muestras por pixel como mucho, más el centro
+const MAX_POINTS: usize = MAX_SAMPLES + 1;
 
-  let triangle_area = edge_function(&a, &b, &c);
+// posición de cada pixel del quad 2×2 (un carril SIMD por pixel)
+const QUAD_X: [i32; 4] = [0, 1, 0, 1];
+const QUAD_Y: [i32; 4] = [0, 0, 1, 1];
+
+/// cómo se recorre el triángulo; dan los mismos fragmentos, el SIMD además descarta lo que está
+/// claramente detrás del zbuffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rasterizer {
+  /// pixel por pixel, queda como referencia
+  Scalar,
+  /// quads de 2×2 pixeles a la vez
+  Simd,
+}
+
+/// agrega a fragments los del triángulo que caen dentro del framebuffer, con la cobertura de sus muestras
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, framebuffer: &Framebuffer, fragments: &mut Vec<Fragments>) {
+  let Some(setup) = Setup::new(v1, v2, v3, framebuffer) else { return };
+
+  match framebuffer.rasterizer {
+    // los muy grandes no entran en i32, son pocos y van por el camino escalar
+    Rasterizer::Simd if setup.extent <= SIMD_MAX_EXTENT << SUBPIXEL_BITS => triangle_simd(&setup, framebuffer, fragments),
+    _ => triangle_scalar(&setup, framebuffer, fragments),
+  }
+}
+
+fn triangle_scalar(setup: &Setup, framebuffer: &Framebuffer, fragments: &mut Vec<Fragments>) {
+  let edges = &setup.edges;
+
+  // el centro va como una muestra más, al final, solo para interpolar
+  let (offsets, points) = setup_offsets(framebuffer.sample_offsets());
+  let center = points - 1;
+
+  // valor de los tres lados en cada muestra del primer pixel de la fila; después solo se suma
+  let mut row = [[0i64; 3]; MAX_POINTS];
+  for (values, offset) in row.iter_mut().zip(&offsets[..points]) {
+    let p = Point { x: (setup.min_x << SUBPIXEL_BITS) + offset.x, y: (setup.min_y << SUBPIXEL_BITS) + offset.y };
+    *values = edges.each_ref().map(|edge| edge.at(p));
+  }
 
   //iteración
-  for y in min_y..=max_y {
-    for x in min_x..=max_x {
-      let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-
-      //  barycentric coordenadas
-      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
-
-      // para ver si está dentro del triangle 
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
-
-        
-        let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-        let normal = normal.normalize();
-
-        // calcula luz
-        let intensity = dot(&normal, &light_dir).max(0.0);
-
-        // depth
-        let depth = a.z * w1 + b.z * w2 + c.z * w3;
-
-      
-        let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
-
-        fragments.push(Fragments::new(
-            Vec2::new(x as f32, y as f32),
-            depth,
-            normal,
-            intensity,
-            vertex_position,
-        ));
+  for y in setup.min_y..=setup.max_y {
+    let mut w = row;
+
+    for x in setup.min_x..=setup.max_x {
+      // qué muestras del pixel caen dentro del triangle, con la regla de arriba-izquierda
+      // para que un punto justo en un lado compartido sea de uno solo de los dos triángulos
+      let mut coverage = 0u8;
+      let mut first_covered = None;
+      for (sample, ws) in w[..center].iter().enumerate() {
+        if edges.iter().zip(ws).all(|(edge, &value)| edge.contains(value)) {
+          coverage |= 1 << sample;
+          first_covered.get_or_insert(*ws);
+        }
+      }
+
+      if let Some(covered) = first_covered {
+        fragments.push(setup.fragment(x, y, w[center], covered, coverage));
+      }
+
+      // un pixel a la derecha
+      for ws in w[..points].iter_mut() {
+        for (value, edge) in ws.iter_mut().zip(edges) {
+          *value += edge.step_x;
+        }
+      }
+    }
+
+    // una fila para abajo
+    for ws in row[..points].iter_mut() {
+      for (value, edge) in ws.iter_mut().zip(edges) {
+        *value += edge.step_y;
+      }
+    }
+  }
+}
+
+// igual que el escalar pero de a 4 pixeles: los lados se evalúan con enteros en SIMD y las
+// muestras que quedan detrás de lo que ya hay en el zbuffer se descartan antes de armar el fragmento
+fn triangle_simd(setup: &Setup, framebuffer: &Framebuffer, fragments: &mut Vec<Fragments>) {
+  let edges = &setup.edges;
+  let samples = framebuffer.samples;
+
+  // los quads empiezan en coordenadas pares
+  let (min_x, min_y) = (setup.min_x & !1, setup.min_y & !1);
+
+  let (offsets, points) = setup_offsets(framebuffer.sample_offsets());
+  let center = points - 1;
+  let lanes_x = i32x4::new(QUAD_X);
+  let lanes_y = i32x4::new(QUAD_Y);
+
+  // con el triángulo chico los lados caben en i32 (ver SIMD_MAX_EXTENT)
+  let step_x = edges.each_ref().map(|edge| edge.step_x as i32);
+  let step_y = edges.each_ref().map(|edge| edge.step_y as i32);
+
+  // por muestra y por lado, el valor en los 4 pixeles del primer quad de la fila
+  let mut row = [[i32x4::ZERO; 3]; MAX_POINTS];
+  for (values, offset) in row.iter_mut().zip(&offsets[..points]) {
+    let p = Point { x: (min_x << SUBPIXEL_BITS) + offset.x, y: (min_y << SUBPIXEL_BITS) + offset.y };
+    for (e, edge) in edges.iter().enumerate() {
+      values[e] = i32x4::splat(edge.at(p) as i32) + lanes_x * step_x[e] + lanes_y * step_y[e];
+    }
+  }
+
+  // adentro es valor > threshold (0 o -1 según la regla de arriba-izquierda)
+  let thresholds = edges.each_ref().map(|edge| i32x4::splat(-1 - edge.bias as i32));
+  let quad_step_x = step_x.map(|step| i32x4::splat(2 * step));
+  let quad_step_y = step_y.map(|step| i32x4::splat(2 * step));
+
+  // desplazamiento de profundidad de cada muestra respecto al centro del pixel
+  let mut sample_depth = [f32x4::ZERO; MAX_SAMPLES];
+  for (depth, offset) in sample_depth.iter_mut().zip(framebuffer.sample_offsets()) {
+    *depth = f32x4::splat(setup.depth_slope.x * (offset.x - 0.5) + setup.depth_slope.y * (offset.y - 0.5));
+  }
+
+  let early_z_slack = f32x4::splat(EARLY_Z_SLACK);
+
+  let mut y = min_y;
+  while y <= setup.max_y {
+    let mut w = row;
+
+    let mut x = min_x;
+    while x <= setup.max_x {
+      // qué pixeles del quad tienen alguna muestra adentro del triángulo
+      let mut inside = [0i32; MAX_SAMPLES];
+      let mut any_inside = 0;
+      for (sample, ws) in w[..center].iter().enumerate() {
+        let mask = ws[0].cmp_gt(thresholds[0]) & ws[1].cmp_gt(thresholds[1]) & ws[2].cmp_gt(thresholds[2]);
+        inside[sample] = mask.move_mask();
+        any_inside |= inside[sample];
+      }
+
+      // pixeles del quad que quedan fuera del framebuffer no cuentan
+      let mut in_bounds = if x < setup.max_x { 0b0011 } else { 0b0001 };
+      if y < setup.max_y {
+        in_bounds |= in_bounds << 2;
+      }
+      any_inside &= in_bounds;
+
+      if any_inside != 0 {
+        // profundidad en el centro de los 4 pixeles
+        let center_x = f32x4::new(QUAD_X.map(|dx| (x + dx as i64) as f32 + 0.5));
+        let center_y = f32x4::new(QUAD_Y.map(|dy| (y + dy as i64) as f32 + 0.5));
+        let depth = setup.depth_plane(center_x, center_y);
+        let first = (y as usize * framebuffer.width + x as usize) * samples;
+        let below = first + framebuffer.width * samples;
+        let index = [first, first + samples, below, below + samples];
+
+        let mut coverage = [0u8; 4];
+        for sample in 0..center {
+          let mut mask = inside[sample] & in_bounds;
+          if mask == 0 {
+            continue;
+          }
+
+          // descarte grueso por depth; la prueba exacta la hace Framebuffer::depth_test y el plano no
+          // da exactamente el mismo float, así que se deja un margen para no perder nada de más
+          let stored = f32x4::new(std::array::from_fn(|lane| {
+            if in_bounds & (1 << lane) != 0 { framebuffer.zbuffer[index[lane] + sample] } else { f32::NEG_INFINITY }
+          }));
+          mask &= (depth + sample_depth[sample]).cmp_lt(stored + early_z_slack).move_mask();
+
+          for (lane, bits) in coverage.iter_mut().enumerate() {
+            if mask & (1 << lane) != 0 {
+              *bits |= 1 << sample;
+            }
+          }
+        }
+
+        for (lane, &bits) in coverage.iter().enumerate() {
+          if bits == 0 {
+            continue;
+          }
+          let lane_values = |ws: &[i32x4; 3]| ws.each_ref().map(|values| values.as_array_ref()[lane] as i64);
+          let covered = lane_values(&w[bits.trailing_zeros() as usize]);
+          let (px, py) = (x + QUAD_X[lane] as i64, y + QUAD_Y[lane] as i64);
+          fragments.push(setup.fragment(px, py, lane_values(&w[center]), covered, bits));
+        }
+      }
+
+      // un quad a la derecha
+      for ws in w[..points].iter_mut() {
+        for (values, step) in ws.iter_mut().zip(&quad_step_x) {
+          *values += *step;
+        }
       }
+      x += 2;
+    }
+
+    // dos filas para abajo
+    for ws in row[..points].iter_mut() {
+      for (values, step) in ws.iter_mut().zip(&quad_step_y) {
+        *values += *step;
+      }
+    }
+    y += 2;
+  }
+}
+
+// lo que se calcula una vez por triángulo, igual para los dos rasterizadores
+struct Setup<'a> {
+  v1: &'a Vertex,
+  v2: &'a Vertex,
+  v3: &'a Vertex,
+  edges: [Edge; 3],
+  // lado más largo de la caja del triángulo, en punto fijo
+  extent: i64,
+  inverse_area: f32,
+  depth_slope: Vec2,
+  min_x: i64,
+  min_y: i64,
+  max_x: i64,
+  max_y: i64,
+}
+
+impl<'a> Setup<'a> {
+  // None si no hay nada que pintar (degenerado, fuera de pantalla o roto)
+  fn new(v1: &'a Vertex, v2: &'a Vertex, v3: &'a Vertex, framebuffer: &Framebuffer) -> Option<Self> {
+    let positions = [v1.transformed_position, v2.transformed_position, v3.transformed_position];
+    if positions.iter().any(|p| !(p.x.abs() < GUARD_BAND && p.y.abs() < GUARD_BAND)) {
+      return None;
+    }
+
+    // se deja siempre con área positiva para que "adentro" sea w >= 0 en los dos sentidos de giro
+    let snap = |p: &Vec3| Point { x: to_fixed(p.x), y: to_fixed(p.y) };
+    let (mut v2, mut v3) = (v2, v3);
+    let mut area = orient(snap(&v1.transformed_position), snap(&v2.transformed_position), snap(&v3.transformed_position));
+    if area == 0 {
+      return None;
+    }
+    if area < 0 {
+      std::mem::swap(&mut v2, &mut v3);
+      area = -area;
+    }
+
+    let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    let (pa, pb, pc) = (snap(&a), snap(&b), snap(&c));
+
+    let min_x = (pa.x.min(pb.x).min(pc.x) >> SUBPIXEL_BITS).max(0);
+    let min_y = (pa.y.min(pb.y).min(pc.y) >> SUBPIXEL_BITS).max(0);
+    let max_x = (pa.x.max(pb.x).max(pc.x) >> SUBPIXEL_BITS).min(framebuffer.width as i64 - 1);
+    let max_y = (pa.y.max(pb.y).max(pc.y) >> SUBPIXEL_BITS).min(framebuffer.height as i64 - 1);
+    if min_x > max_x || min_y > max_y {
+      return None;
     }
+
+    // la profundidad es un plano en pantalla, su pendiente sirve para cada muestra
+    let triangle_area = edge_function(&a, &b, &c);
+    let depth_slope = Vec2::new(
+      (a.z * (c.y - b.y) + b.z * (a.y - c.y) + c.z * (b.y - a.y)) / triangle_area,
+      -(a.z * (c.x - b.x) + b.z * (a.x - c.x) + c.z * (b.x - a.x)) / triangle_area,
+    );
+
+    Some(Setup {
+      v1,
+      v2,
+      v3,
+      // w1 es el lado opuesto a v1 (b→c), w2 el opuesto a v2 (c→a), w3 el opuesto a v3 (a→b)
+      edges: [Edge::new(pb, pc), Edge::new(pc, pa), Edge::new(pa, pb)],
+      extent: (pa.x.max(pb.x).max(pc.x) - pa.x.min(pb.x).min(pc.x)).max(pa.y.max(pb.y).max(pc.y) - pa.y.min(pb.y).min(pc.y)),
+      inverse_area: 1.0 / area as f32,
+      depth_slope,
+      min_x,
+      min_y,
+      max_x,
+      max_y,
+    })
+  }
+
+  fn depth_plane(&self, x: f32x4, y: f32x4) -> f32x4 {
+    let a = self.v1.transformed_position;
+    let dx = x - f32x4::splat(a.x);
+    let dy = y - f32x4::splat(a.y);
+    dx.mul_add(f32x4::splat(self.depth_slope.x), dy.mul_add(f32x4::splat(self.depth_slope.y), f32x4::splat(a.z)))
+  }
+
+  // center son los lados en el centro del pixel y covered en la primera muestra cubierta
+  fn fragment(&self, x: i64, y: i64, center: [i64; 3], covered: [i64; 3], coverage: u8) -> Fragments {
+    let weights = |ws: [i64; 3]| ws.map(|value| value as f32 * self.inverse_area);
+    let (v1, v2, v3) = (self.v1, self.v2, self.v3);
+
+    // se sombrea una vez por pixel: en el centro si está adentro, si no en la primera muestra cubierta
+    let center_weights = weights(center);
+    let [w1, w2, w3] = if center.iter().all(|&value| value >= 0) { center_weights } else { weights(covered) };
+
+    let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
+    let normal = normal.normalize();
+    let tangent = v1.transformed_tangent * w1 + v2.transformed_tangent * w2 + v3.transformed_tangent * w3;
+
+    // calcula luz
+    let intensity = dot(&normal, &LIGHT_DIR).max(0.0);
+
+    // depth en el centro del pixel, cada muestra le suma la pendiente
+    let [c1, c2, c3] = center_weights;
+    let depth = v1.transformed_position.z * c1 + v2.transformed_position.z * c2 + v3.transformed_position.z * c3;
+
+    let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+    let tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+
+    Fragments {
+      position: Vec2::new(x as f32, y as f32),
+      depth,
+      normal,
+      tangent,
+      intensity,
+      vertex_pos: vertex_position,
+      tex_coords,
+      coverage,
+      depth_slope: self.depth_slope,
+    }
+  }
+}
+
+// muestras del framebuffer en punto fijo, más el centro del pixel al final
+fn setup_offsets(sample_offsets: &[Vec2]) -> ([Point; MAX_POINTS], usize) {
+  let mut offsets = [Point { x: 0, y: 0 }; MAX_POINTS];
+  let center = Vec2::new(0.5, 0.5);
+  for (point, offset) in offsets.iter_mut().zip(sample_offsets.iter().chain(std::iter::once(&center))) {
+    *point = Point { x: to_fixed(offset.x), y: to_fixed(offset.y) };
   }
+  (offsets, sample_offsets.len() + 1)
+}
+
+#[derive(Clone, Copy)]
+struct Point {
+  x: i64,
+  y: i64,
+}
 
-  fragments
+fn to_fixed(value: f32) -> i64 {
+  (value * SUBPIXEL_ONE as f32).round() as i64
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+// mismo signo que edge_function, en enteros
+fn orient(a: Point, b: Point, p: Point) -> i64 {
+  (p.x - a.x) * (b.y - a.y) - (p.y - a.y) * (b.x - a.x)
+}
 
-    (min_x, min_y, max_x, max_y)
+// un lado del triángulo visto como función lineal de la posición
+struct Edge {
+  origin: Point,
+  end: Point,
+  // cuánto cambia al moverse un pixel entero
+  step_x: i64,
+  step_y: i64,
+  // 0 si el lado es de arriba o de la izquierda (se queda con los empates), -1 si no
+  bias: i64,
 }
 
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
-    let w1 = edge_function(b, c, p) / area;
-    let w2 = edge_function(c, a, p) / area;
-    let w3 = edge_function(a, b, p) / area;
+impl Edge {
+  fn new(origin: Point, end: Point) -> Self {
+    let (dx, dy) = (end.x - origin.x, end.y - origin.y);
+    // crece hacia la derecha = lado izquierdo; horizontal y crece hacia abajo = lado de arriba
+    let top_left = dy > 0 || (dy == 0 && dx < 0);
+
+    Edge {
+      origin,
+      end,
+      step_x: dy * SUBPIXEL_ONE,
+      step_y: -dx * SUBPIXEL_ONE,
+      bias: if top_left { 0 } else { -1 },
+    }
+  }
 
-    (w1, w2, w3)
+  fn at(&self, p: Point) -> i64 {
+    orient(self.origin, self.end, p)
+  }
+
+  fn contains(&self, value: i64) -> bool {
+    value + self.bias >= 0
+  }
 }
 
-fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
+pub fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
 
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SIZE: usize = 24;
+  const RASTERIZERS: [Rasterizer; 2] = [Rasterizer::Scalar, Rasterizer::Simd];
+
+  fn vertex(x: f32, y: f32) -> Vertex {
+    let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros());
+    vertex.transformed_position = Vec3::new(x, y, 0.5);
+    vertex
+  }
+
+  fn framebuffer(samples: usize, rasterizer: Rasterizer) -> Framebuffer {
+    let mut framebuffer = Framebuffer::with_samples(SIZE, SIZE, samples);
+    framebuffer.rasterizer = rasterizer;
+    framebuffer
+  }
+
+  // cuántas veces se cubre cada muestra de cada pixel
+  fn coverage_counts(triangles: &[[Vertex; 3]], framebuffer: &Framebuffer) -> Vec<u32> {
+    let samples = framebuffer.samples;
+    let mut counts = vec![0; SIZE * SIZE * samples];
+    let mut fragments = Vec::new();
+    for [v1, v2, v3] in triangles {
+      triangle(v1, v2, v3, framebuffer, &mut fragments);
+    }
+    for fragment in &fragments {
+      let pixel = fragment.position.y as usize * SIZE + fragment.position.x as usize;
+      for sample in 0..samples {
+        if fragment.coverage & (1 << sample) != 0 {
+          counts[pixel * samples + sample] += 1;
+        }
+      }
+    }
+    counts
+  }
+
+  // cuadrado partido en una grilla de n×n celdas, cada una en dos triángulos con la diagonal
+  // alternada, más un abanico alrededor de un vértice interno para tener lados inclinados
+  fn tessellated_square(min: f32, max: f32, n: usize) -> Vec<[Vertex; 3]> {
+    let step = (max - min) / n as f32;
+    let at = |i: usize, j: usize| vertex(min + i as f32 * step, min + j as f32 * step);
+    let mut triangles = Vec::new();
+    for j in 0..n {
+      for i in 0..n {
+        let (p00, p10, p01, p11) = (at(i, j), at(i + 1, j), at(i, j + 1), at(i + 1, j + 1));
+        if (i + j) % 3 == 0 {
+          // abanico con un centro corrido para que los lados no sean de 45°
+          let mid = vertex(min + (i as f32 + 0.37) * step, min + (j as f32 + 0.61) * step);
+          triangles.push([p00.clone(), p10.clone(), mid.clone()]);
+          triangles.push([p10, p11.clone(), mid.clone()]);
+          triangles.push([p11, p01.clone(), mid.clone()]);
+          triangles.push([p01, p00, mid]);
+        } else if (i + j) % 2 == 0 {
+          triangles.push([p00.clone(), p10, p11.clone()]);
+          triangles.push([p00, p11, p01]);
+        } else {
+          // al revés de giro, el rasterizador tiene que aceptar los dos
+          triangles.push([p00, p01.clone(), p10.clone()]);
+          triangles.push([p10, p01, p11]);
+        }
+      }
+    }
+    triangles
+  }
+
+  // las muestras dentro del cuadrado (o justo en el lado de arriba/izquierda) se cubren una vez,
+  // las de afuera ninguna
+  fn assert_exact_cover(min: f32, max: f32, n: usize, samples: usize) {
+    for rasterizer in RASTERIZERS {
+      let framebuffer = framebuffer(samples, rasterizer);
+      let counts = coverage_counts(&tessellated_square(min, max, n), &framebuffer);
+      for y in 0..SIZE {
+        for x in 0..SIZE {
+          for (s, offset) in framebuffer.sample_offsets().iter().enumerate() {
+            let (sx, sy) = (x as f32 + offset.x, y as f32 + offset.y);
+            let inside = (min..max).contains(&sx) && (min..max).contains(&sy);
+            let count = counts[(y * SIZE + x) * samples + s];
+            assert_eq!(count, inside as u32, "{:?}: muestra {} del pixel ({}, {})", rasterizer, s, x, y);
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn square_with_vertices_on_pixel_centers_covers_each_pixel_once() {
+    // todos los lados pasan justo por centros de pixel: solo la regla de empate decide
+    assert_exact_cover(2.5, 18.5, 8, 1);
+  }
+
+  #[test]
+  fn square_with_fractional_vertices_covers_each_pixel_once() {
+    assert_exact_cover(1.3, 21.7, 7, 1);
+  }
+
+  #[test]
+  fn square_covers_each_msaa_sample_once() {
+    assert_exact_cover(2.0, 20.0, 9, 4);
+    assert_exact_cover(1.3, 21.7, 7, 4);
+  }
+
+  #[test]
+  fn degenerate_triangle_has_no_fragments() {
+    for rasterizer in RASTERIZERS {
+      let mut fragments = Vec::new();
+      triangle(&vertex(1.0, 1.0), &vertex(5.0, 5.0), &vertex(9.0, 9.0), &framebuffer(1, rasterizer), &mut fragments);
+      assert!(fragments.is_empty());
+    }
+  }
 
+  #[test]
+  fn simd_matches_scalar() {
+    let triangles = [
+      [vertex(0.2, 0.7), vertex(23.1, 3.4), vertex(9.9, 22.8)],
+      [vertex(5.5, 5.5), vertex(5.5, 17.5), vertex(18.25, 9.0)],
+      [vertex(-4.0, 2.0), vertex(30.0, 11.3), vertex(3.0, 30.0)],
+    ];
+    for samples in [1, 4] {
+      for [v1, v2, v3] in &triangles {
+        let rasterize = |rasterizer| {
+          let mut fragments = Vec::new();
+          triangle(v1, v2, v3, &framebuffer(samples, rasterizer), &mut fragments);
+          let mut keys: Vec<_> = fragments.iter().map(|f| (f.position.x as i32, f.position.y as i32, f.coverage)).collect();
+          keys.sort();
+          keys
+        };
+        assert_eq!(rasterize(Rasterizer::Scalar), rasterize(Rasterizer::Simd));
+      }
+    }
+  }
+
+  #[test]
+  fn simd_rejects_samples_behind_the_zbuffer() {
+    let mut framebuffer = framebuffer(1, Rasterizer::Simd);
+    // algo más cerca (0.25) tapa la mitad de arriba
+    for depth in framebuffer.zbuffer[..SIZE * SIZE / 2].iter_mut() {
+      *depth = 0.25;
+    }
+    let mut fragments = Vec::new();
+    triangle(&vertex(0.0, 0.0), &vertex(SIZE as f32, 0.0), &vertex(0.0, SIZE as f32), &framebuffer, &mut fragments);
+    assert!(!fragments.is_empty());
+    assert!(fragments.iter().all(|f| f.position.y as usize >= SIZE / 2));
+  }
+}
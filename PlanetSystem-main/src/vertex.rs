@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -6,9 +6,12 @@ pub struct Vertex {
   pub position: Vec3,
   pub normal: Vec3,
   pub tex_coords: Vec2,
+  // dirección de u en la superficie; w es el signo para sacar la bitangente (cero si no hay)
+  pub tangent: Vec4,
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub transformed_tangent: Vec4,
 }
 
 impl Vertex {
@@ -17,9 +20,11 @@ impl Vertex {
       position,
       normal,
       tex_coords,
+      tangent: Vec4::zeros(),
       color: Color::BLACK,
       transformed_position: position,
       transformed_normal: normal,
+      transformed_tangent: Vec4::zeros(),
     }
   }
 }
@@ -30,9 +35,11 @@ impl Default for Vertex {
       position: Vec3::new(0.0, 0.0, 0.0),
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
+      tangent: Vec4::zeros(),
       color: Color::BLACK,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      transformed_tangent: Vec4::zeros(),
     }
   }
 }
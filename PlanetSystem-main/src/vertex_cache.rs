@@ -0,0 +1,127 @@
+use crate::displacement::Displacement;
+use crate::mesh::Mesh;
+use crate::shaders::{vertex_shader, VertexTransform};
+use crate::vertex::Vertex;
+
+/// vértices ya transformados de la instancia que se está dibujando: cada índice pasa por el
+/// vertex shader una sola vez aunque lo compartan varios triángulos
+#[derive(Default)]
+pub struct VertexCache {
+    slots: Vec<Vertex>,
+    // un slot es válido si su marca es la de la instancia actual, así no hay que vaciarlo
+    stamps: Vec<u32>,
+    stamp: u32,
+}
+
+impl VertexCache {
+    /// empieza una instancia nueva de un mesh con vertex_count vértices
+    pub fn reset(&mut self, vertex_count: usize) {
+        if self.slots.len() < vertex_count {
+            self.slots.resize(vertex_count, Vertex::default());
+            self.stamps.resize(vertex_count, 0);
+        }
+        self.stamp = self.stamp.wrapping_add(1);
+        if self.stamp == 0 {
+            // dio la vuelta, las marcas viejas podrían coincidir
+            self.stamps.fill(0);
+            self.stamp = 1;
+        }
+    }
+
+    /// transforma el vértice si todavía no está (desplazado primero si hay relieve); devuelve
+    /// true si hubo que correr el shader. un índice fuera del mesh no tiene nada que transformar
+    /// y get da None
+    pub fn fetch(&mut self, index: u32, mesh: &Mesh, transform: &VertexTransform, displacement: Option<&Displacement>) -> bool {
+        let i = index as usize;
+        let Some(vertex) = mesh.vertices.get(i) else { return false };
+        if self.stamps[i] == self.stamp {
+            return false;
+        }
+        self.slots[i] = match displacement {
+            Some(displacement) => vertex_shader(&displacement.displace(vertex), transform),
+            None => vertex_shader(vertex, transform),
+        };
+        self.stamps[i] = self.stamp;
+        true
+    }
+
+    /// el vértice ya transformado, solo si se pidió en esta instancia
+    pub fn get(&self, index: u32) -> Option<&Vertex> {
+        let i = index as usize;
+        (self.stamps.get(i) == Some(&self.stamp)).then(|| &self.slots[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Uniforms;
+    use nalgebra_glm::{self as glm, Mat4, Vec2, Vec3};
+
+    fn transform(offset: f32) -> VertexTransform {
+        VertexTransform::new(&Uniforms {
+            model_matrix: glm::translation(&Vec3::new(offset, 0.0, 0.0)),
+            view_matrix: Mat4::identity(),
+            projection_matrix: Mat4::identity(),
+            viewport_matrix: Mat4::identity(),
+            time: 0,
+            debug_mode: 0,
+            camera_position: Vec3::zeros(),
+            light_position: Vec3::zeros(),
+        })
+    }
+
+    fn triangle() -> Mesh {
+        let vertex = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), Vec3::z(), Vec2::zeros());
+        Mesh::new(vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 1.0)], vec![0, 1, 2])
+    }
+
+    #[test]
+    fn repeated_index_keeps_the_first_transform() {
+        let mesh = triangle();
+        let mut cache = VertexCache::default();
+        cache.reset(mesh.vertices.len());
+
+        assert!(cache.fetch(1, &mesh, &transform(0.0), None));
+        // con otra matriz no se vuelve a correr el shader, queda lo de la primera vez
+        assert!(!cache.fetch(1, &mesh, &transform(5.0), None));
+        assert_eq!(cache.get(1).unwrap().transformed_position.x, 1.0);
+    }
+
+    #[test]
+    fn reset_invalidates_every_slot() {
+        let mesh = triangle();
+        let mut cache = VertexCache::default();
+        cache.reset(mesh.vertices.len());
+        cache.fetch(1, &mesh, &transform(0.0), None);
+
+        cache.reset(mesh.vertices.len());
+        assert!(cache.get(1).is_none());
+        assert!(cache.fetch(1, &mesh, &transform(5.0), None));
+        assert_eq!(cache.get(1).unwrap().transformed_position.x, 6.0);
+    }
+
+    #[test]
+    fn indices_outside_the_mesh_are_skipped() {
+        let mesh = triangle();
+        let mut cache = VertexCache::default();
+        cache.reset(mesh.vertices.len());
+        assert!(!cache.fetch(3, &mesh, &transform(0.0), None));
+        assert!(cache.get(3).is_none());
+        assert!(cache.get(1000).is_none());
+    }
+
+    #[test]
+    fn displacement_hook_moves_the_vertex_before_the_shader() {
+        let mesh = triangle();
+        let mut cache = VertexCache::default();
+        cache.reset(mesh.vertices.len());
+
+        // altura 1 en todos lados: se sale 0.25 por la normal (+z)
+        let displacement = Displacement::procedural(|_| 1.0, 0.25);
+        assert!(cache.fetch(1, &mesh, &transform(0.0), Some(&displacement)));
+        let vertex = cache.get(1).unwrap();
+        assert_eq!(vertex.transformed_position.x, 1.0);
+        assert!((vertex.position.z - 0.25).abs() < 1e-6);
+    }
+}